@@ -0,0 +1,110 @@
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use crate::models::CachedArticle;
+
+/// The cached extraction of `url` for `config_hash`, if it was stored after `fresh_since`.
+pub fn get_cached_article(
+    conn: &Connection,
+    url: &str,
+    config_hash: &str,
+    fresh_since: DateTime<Utc>,
+) -> rusqlite::Result<Option<CachedArticle>> {
+    conn.query_row(
+        "SELECT url, title, content, source_url, pages, author, published, lead_image, canonical_url FROM article_cache
+         WHERE url = ?1 AND config_hash = ?2 AND created_at >= ?3",
+        params![url, config_hash, fresh_since.to_rfc3339()],
+        |row| {
+            let published: Option<String> = row.get(6)?;
+            Ok(CachedArticle {
+                url: row.get(0)?,
+                title: row.get(1)?,
+                content: row.get(2)?,
+                source_url: row.get(3)?,
+                pages: row.get(4)?,
+                author: row.get(5)?,
+                published: published
+                    .and_then(|p| DateTime::parse_from_rfc3339(&p).ok())
+                    .map(|p| p.with_timezone(&Utc)),
+                lead_image: row.get(7)?,
+                canonical_url: row.get(8)?,
+            })
+        },
+    )
+    .optional()
+}
+
+pub fn save_cached_article(conn: &Connection, config_hash: &str, article: &CachedArticle) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO article_cache (url, config_hash, title, content, source_url, pages, author, published, lead_image, canonical_url, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        params![
+            article.url,
+            config_hash,
+            article.title,
+            article.content,
+            article.source_url,
+            article.pages,
+            article.author,
+            article.published.map(|p| p.to_rfc3339()),
+            article.lead_image,
+            article.canonical_url,
+            Utc::now().to_rfc3339()
+        ],
+    )?;
+    Ok(())
+}
+
+/// Removes every cached extraction of `url`, returning how many were removed.
+pub fn delete_cached_article(conn: &Connection, url: &str) -> rusqlite::Result<usize> {
+    conn.execute("DELETE FROM article_cache WHERE url = ?1", params![url])
+}
+
+pub fn clear_article_cache(conn: &Connection) -> rusqlite::Result<usize> {
+    conn.execute("DELETE FROM article_cache", [])
+}
+
+pub fn prune_article_cache(conn: &Connection, older_than: DateTime<Utc>) -> rusqlite::Result<usize> {
+    conn.execute(
+        "DELETE FROM article_cache WHERE created_at < ?1",
+        params![older_than.to_rfc3339()],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::schema_init;
+    use chrono::Duration;
+
+    #[test]
+    fn test_article_cache_round_trip() {
+        let conn = schema_init::init_db(":memory:").unwrap();
+        let url = "https://example.com/post";
+        let hour_ago = Utc::now() - Duration::hours(1);
+        assert!(get_cached_article(&conn, url, "abc", hour_ago).unwrap().is_none());
+
+        let article = CachedArticle {
+            url: url.to_string(),
+            title: "Post".to_string(),
+            content: "<p>Body</p>".to_string(),
+            source_url: url.to_string(),
+            pages: 2,
+            author: Some("Jane".to_string()),
+            published: None,
+            lead_image: None,
+            canonical_url: Some("https://example.com/post".to_string()),
+        };
+        save_cached_article(&conn, "abc", &article).unwrap();
+
+        let cached = get_cached_article(&conn, url, "abc", hour_ago).unwrap().unwrap();
+        assert_eq!(cached.content, "<p>Body</p>");
+        assert_eq!(cached.pages, 2);
+        assert_eq!(cached.author.as_deref(), Some("Jane"));
+        // Another processor configuration, or an expired entry, misses.
+        assert!(get_cached_article(&conn, url, "def", hour_ago).unwrap().is_none());
+        assert!(get_cached_article(&conn, url, "abc", Utc::now() + Duration::hours(1)).unwrap().is_none());
+
+        assert_eq!(delete_cached_article(&conn, url).unwrap(), 1);
+        assert!(get_cached_article(&conn, url, "abc", hour_ago).unwrap().is_none());
+    }
+}
@@ -1,5 +1,6 @@
 use chrono::Utc;
 use rusqlite::{params, Connection};
+use crate::db::filter_rule_db;
 use crate::models::{Category, CategoryPosition};
 
 pub fn add_category(conn: &Connection, name: &str) -> rusqlite::Result<i64> {
@@ -29,11 +30,17 @@ pub fn get_categories(conn: &Connection) -> rusqlite::Result<Vec<Category>> {
             id: Some(row.get(0)?),
             name: row.get(1)?,
             position: row.get(2)?,
+            filter_rules: Vec::new(),
         })
     })?;
 
     let mut cats = Vec::new();
     for curr in iter { cats.push(curr?); }
+
+    let rules = filter_rule_db::get_filter_rules(conn)?;
+    for cat in cats.iter_mut() {
+        cat.filter_rules = rules.iter().filter(|r| r.category_id.is_some() && r.category_id == cat.id).cloned().collect();
+    }
     Ok(cats)
 }
 
@@ -57,6 +64,7 @@ pub fn reorder_categories(conn: &Connection, positions: &Vec<CategoryPosition>)
 }
 
 pub fn delete_category(conn: &Connection, id: i64) -> rusqlite::Result<()> {
+    conn.execute("DELETE FROM filter_rules WHERE category_id = ?1", params![id])?;
     conn.execute("DELETE FROM categories WHERE id = ?1", params![id])?;
     Ok(())
 }
@@ -0,0 +1,118 @@
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection};
+use crate::models::DeliveredArticles;
+
+pub fn get_delivered_articles(conn: &Connection, schedule_id: i64) -> rusqlite::Result<DeliveredArticles> {
+    let mut stmt = conn.prepare("SELECT entry_id, link FROM delivered_articles WHERE schedule_id = ?1")?;
+    let rows = stmt.query_map(params![schedule_id], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?))
+    })?;
+
+    let mut delivered = DeliveredArticles::default();
+    for row in rows {
+        let (entry_id, link) = row?;
+        delivered.entry_ids.insert(entry_id);
+        if let Some(link) = link.filter(|l| !l.is_empty()) {
+            delivered.links.insert(link);
+        }
+    }
+    Ok(delivered)
+}
+
+/// When the schedule last delivered anything, if its ledger has entries.
+pub fn last_delivered_at(conn: &Connection, schedule_id: i64) -> rusqlite::Result<Option<DateTime<Utc>>> {
+    let last: Option<String> = conn.query_row(
+        "SELECT MAX(delivered_at) FROM delivered_articles WHERE schedule_id = ?1",
+        params![schedule_id],
+        |row| row.get(0),
+    )?;
+    Ok(last
+        .and_then(|t| DateTime::parse_from_rfc3339(&t).ok())
+        .map(|t| t.with_timezone(&Utc)))
+}
+
+/// Records `(entry_id, link)` pairs as delivered by the schedule.
+pub fn record_delivered_articles(
+    conn: &Connection,
+    schedule_id: i64,
+    articles: &[(String, String)],
+) -> rusqlite::Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    {
+        let mut stmt = tx.prepare(
+            "INSERT OR REPLACE INTO delivered_articles (schedule_id, entry_id, link, delivered_at) VALUES (?1, ?2, ?3, ?4)",
+        )?;
+        let delivered_at = Utc::now().to_rfc3339();
+        for (entry_id, link) in articles {
+            stmt.execute(params![schedule_id, entry_id, link, delivered_at])?;
+        }
+    }
+    tx.commit()
+}
+
+pub fn prune_delivered_articles(conn: &Connection, older_than: DateTime<Utc>) -> rusqlite::Result<usize> {
+    conn.execute(
+        "DELETE FROM delivered_articles WHERE delivered_at < ?1",
+        params![older_than.to_rfc3339()],
+    )
+}
+
+pub fn delete_schedule_deliveries(conn: &Connection, schedule_id: i64) -> rusqlite::Result<()> {
+    conn.execute("DELETE FROM delivered_articles WHERE schedule_id = ?1", params![schedule_id])?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{self, schema_init};
+    use crate::models::{ArticleSelection, Schedule};
+    use chrono::Duration;
+
+    fn add_schedule(conn: &Connection) -> i64 {
+        let schedule = Schedule {
+            id: None,
+            cron_expression: "0 0 7 * * *".to_string(),
+            active: true,
+            schedule_type: "rss".to_string(),
+            timezone: "UTC".to_string(),
+            category_ids: Vec::new(),
+            override_to_email: None,
+            fetch_since_hours_override: None,
+            article_selection: ArticleSelection::Undelivered,
+        };
+        db::add_schedule(conn, &schedule).unwrap();
+        conn.last_insert_rowid()
+    }
+
+    #[test]
+    fn test_delivered_articles_are_tracked_per_schedule() {
+        let conn = schema_init::init_db(":memory:").unwrap();
+        let schedule_id = add_schedule(&conn);
+        let other_schedule_id = add_schedule(&conn);
+        record_delivered_articles(
+            &conn,
+            schedule_id,
+            &[
+                ("urn:post:1".to_string(), "https://example.com/1".to_string()),
+                ("urn:post:2".to_string(), String::new()),
+            ],
+        )
+        .unwrap();
+
+        let delivered_at = last_delivered_at(&conn, schedule_id).unwrap().unwrap();
+        assert!(delivered_at > Utc::now() - Duration::minutes(1));
+        assert!(last_delivered_at(&conn, other_schedule_id).unwrap().is_none());
+
+        let delivered = get_delivered_articles(&conn, schedule_id).unwrap();
+        assert!(delivered.contains("urn:post:1", ""));
+        assert!(delivered.contains("urn:post:re-dated", "https://example.com/1"));
+        assert!(delivered.contains("urn:post:2", "https://example.com/2"));
+        assert!(!delivered.contains("urn:post:3", "https://example.com/3"));
+        assert!(!get_delivered_articles(&conn, other_schedule_id).unwrap().contains("urn:post:1", ""));
+        assert_eq!(db::get_schedules(&conn).unwrap()[0].article_selection, ArticleSelection::Undelivered);
+
+        assert_eq!(prune_delivered_articles(&conn, Utc::now() - Duration::days(1)).unwrap(), 0);
+        assert_eq!(prune_delivered_articles(&conn, Utc::now() + Duration::days(1)).unwrap(), 2);
+    }
+}
@@ -0,0 +1,59 @@
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension};
+use crate::models::FeedHttpCache;
+
+pub fn get_feed_cache(conn: &Connection, url: &str) -> rusqlite::Result<Option<FeedHttpCache>> {
+    conn.query_row(
+        "SELECT url, etag, last_modified, parsed_feed FROM feed_http_cache WHERE url = ?1",
+        params![url],
+        |row| {
+            Ok(FeedHttpCache {
+                url: row.get(0)?,
+                etag: row.get(1)?,
+                last_modified: row.get(2)?,
+                parsed_feed: row.get(3)?,
+            })
+        },
+    )
+    .optional()
+}
+
+pub fn save_feed_cache(conn: &Connection, cache: &FeedHttpCache) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO feed_http_cache (url, etag, last_modified, parsed_feed, updated_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![cache.url, cache.etag, cache.last_modified, cache.parsed_feed, Utc::now().to_rfc3339()],
+    )?;
+    Ok(())
+}
+
+pub fn delete_feed_cache(conn: &Connection, url: &str) -> rusqlite::Result<()> {
+    conn.execute("DELETE FROM feed_http_cache WHERE url = ?1", params![url])?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::schema_init;
+
+    #[test]
+    fn test_feed_cache_round_trip() {
+        let conn = schema_init::init_db(":memory:").unwrap();
+        assert!(get_feed_cache(&conn, "https://example.com/feed").unwrap().is_none());
+
+        let cache = FeedHttpCache {
+            url: "https://example.com/feed".to_string(),
+            etag: Some("\"abc\"".to_string()),
+            last_modified: None,
+            parsed_feed: "{}".to_string(),
+        };
+        save_feed_cache(&conn, &cache).unwrap();
+
+        let fetched = get_feed_cache(&conn, "https://example.com/feed").unwrap().unwrap();
+        assert_eq!(fetched.etag.as_deref(), Some("\"abc\""));
+        assert_eq!(fetched.last_modified, None);
+
+        delete_feed_cache(&conn, "https://example.com/feed").unwrap();
+        assert!(get_feed_cache(&conn, "https://example.com/feed").unwrap().is_none());
+    }
+}
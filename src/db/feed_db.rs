@@ -1,6 +1,7 @@
 use chrono::Utc;
 use rusqlite::{params, Connection};
-use crate::models::{ContentProcessor, Feed, FeedPosition, ProcessorType};
+use crate::db::{feed_request_options_db, filter_rule_db};
+use crate::models::{ArticleOrder, ContentProcessor, Feed, FeedPosition, FeedSourceType, ProcessorType};
 
 pub fn add_feed(
     conn: &Connection,
@@ -34,10 +35,43 @@ pub fn update_feed(
     Ok(())
 }
 
+/// Updates the per-feed article limit; `None` keeps the stored value.
+pub fn save_feed_article_limit(
+    conn: &Connection,
+    feed_id: i64,
+    max_articles: Option<usize>,
+    article_order: Option<ArticleOrder>,
+) -> rusqlite::Result<()> {
+    conn.execute(
+        "UPDATE feeds SET max_articles = COALESCE(?1, max_articles), article_order = COALESCE(?2, article_order) WHERE id = ?3",
+        params![max_articles, article_order.map(ArticleOrder::as_str), feed_id],
+    )?;
+    Ok(())
+}
+
+pub fn save_feed_source(
+    conn: &Connection,
+    feed_id: i64,
+    source_type: FeedSourceType,
+    scrape_config: Option<&str>,
+) -> rusqlite::Result<()> {
+    conn.execute(
+        "UPDATE feeds SET source_type = ?1, scrape_config = ?2 WHERE id = ?3",
+        params![source_type.as_str(), scrape_config, feed_id],
+    )?;
+    // A cached parse of the page may have been produced with other selectors.
+    conn.execute(
+        "DELETE FROM feed_http_cache WHERE url = (SELECT url FROM feeds WHERE id = ?1)",
+        params![feed_id],
+    )?;
+    Ok(())
+}
+
 pub fn get_feeds(conn: &Connection) -> rusqlite::Result<Vec<Feed>> {
     let mut stmt = conn.prepare(
         "SELECT f.id, f.url, f.name, f.concurrency_limit, f.position, fp.processor, fp.custom_config,
-                fc.category_id, c.name
+                fc.category_id, c.name, f.paused, f.max_articles, f.article_order,
+                f.source_type, f.scrape_config, fp.fallback_processors
          FROM feeds f
          LEFT JOIN feed_processor fp ON f.id = fp.feed_id
          LEFT JOIN feed_category fc ON f.id = fc.feed_id
@@ -51,6 +85,7 @@ pub fn get_feeds(conn: &Connection) -> rusqlite::Result<Vec<Feed>> {
             .map(ProcessorType::from_i32)
             .unwrap_or(ProcessorType::Default);
         let custom_config: Option<String> = row.get(6)?;
+        let fallback_processors: Option<String> = row.get(14)?;
 
         let category_id: Option<i64> = row.get(7)?;
         let category: Option<String> = row.get(8)?;
@@ -65,9 +100,19 @@ pub fn get_feeds(conn: &Connection) -> rusqlite::Result<Vec<Feed>> {
                 id: Some(feed_id),
                 processor,
                 custom_config,
+                fallback_processors: fallback_processors
+                    .and_then(|f| serde_json::from_str(&f).ok())
+                    .unwrap_or_default(),
             },
             category_id,
             category,
+            paused: row.get(9)?,
+            filter_rules: Vec::new(),
+            max_articles: row.get(10)?,
+            article_order: ArticleOrder::from_db(&row.get::<_, String>(11)?),
+            source_type: FeedSourceType::from_db(&row.get::<_, String>(12)?),
+            scrape_config: row.get(13)?,
+            request_options: None,
         })
     })?;
 
@@ -75,13 +120,16 @@ pub fn get_feeds(conn: &Connection) -> rusqlite::Result<Vec<Feed>> {
     for feed in feed_iter {
         feeds.push(feed?);
     }
+    attach_filter_rules(conn, &mut feeds)?;
+    attach_request_option_summaries(conn, &mut feeds)?;
     Ok(feeds)
 }
 
 pub fn get_feeds_by_category(conn: &Connection, search_cat_id: i64) -> rusqlite::Result<Vec<Feed>> {
     let mut stmt = conn.prepare(
         "SELECT f.id, f.url, f.name, f.concurrency_limit, f.position, fp.processor, fp.custom_config,
-                fc.category_id, c.name
+                fc.category_id, c.name, f.paused, f.max_articles, f.article_order,
+                f.source_type, f.scrape_config, fp.fallback_processors
          FROM feeds f
          LEFT JOIN feed_processor fp ON f.id = fp.feed_id
          JOIN feed_category fc ON f.id = fc.feed_id
@@ -96,6 +144,7 @@ pub fn get_feeds_by_category(conn: &Connection, search_cat_id: i64) -> rusqlite:
             .map(ProcessorType::from_i32)
             .unwrap_or(ProcessorType::Default);
         let custom_config: Option<String> = row.get(6)?;
+        let fallback_processors: Option<String> = row.get(14)?;
 
         let category_id: Option<i64> = row.get(7)?;
         let category: Option<String> = row.get(8)?;
@@ -110,9 +159,19 @@ pub fn get_feeds_by_category(conn: &Connection, search_cat_id: i64) -> rusqlite:
                 id: Some(feed_id),
                 processor,
                 custom_config,
+                fallback_processors: fallback_processors
+                    .and_then(|f| serde_json::from_str(&f).ok())
+                    .unwrap_or_default(),
             },
             category_id,
             category,
+            paused: row.get(9)?,
+            filter_rules: Vec::new(),
+            max_articles: row.get(10)?,
+            article_order: ArticleOrder::from_db(&row.get::<_, String>(11)?),
+            source_type: FeedSourceType::from_db(&row.get::<_, String>(12)?),
+            scrape_config: row.get(13)?,
+            request_options: None,
         })
     })?;
 
@@ -120,9 +179,27 @@ pub fn get_feeds_by_category(conn: &Connection, search_cat_id: i64) -> rusqlite:
     for feed in feed_iter {
         feeds.push(feed?);
     }
+    attach_filter_rules(conn, &mut feeds)?;
+    attach_request_option_summaries(conn, &mut feeds)?;
     Ok(feeds)
 }
 
+fn attach_filter_rules(conn: &Connection, feeds: &mut [Feed]) -> rusqlite::Result<()> {
+    let rules = filter_rule_db::get_filter_rules(conn)?;
+    for feed in feeds.iter_mut() {
+        feed.filter_rules = rules.iter().filter(|r| r.feed_id.is_some() && r.feed_id == feed.id).cloned().collect();
+    }
+    Ok(())
+}
+
+fn attach_request_option_summaries(conn: &Connection, feeds: &mut [Feed]) -> rusqlite::Result<()> {
+    let mut summaries = feed_request_options_db::get_request_option_summaries(conn)?;
+    for feed in feeds.iter_mut() {
+        feed.request_options = feed.id.and_then(|id| summaries.remove(&id));
+    }
+    Ok(())
+}
+
 pub fn reorder_feeds(conn: &Connection, feed_positions: &Vec<FeedPosition>) -> rusqlite::Result<()> {
     let mut stmt = conn.prepare("UPDATE feeds SET position = ?1 WHERE id = ?2")?;
     for x in feed_positions {
@@ -132,6 +209,13 @@ pub fn reorder_feeds(conn: &Connection, feed_positions: &Vec<FeedPosition>) -> r
 }
 
 pub fn delete_feed(conn: &Connection, id: i64) -> rusqlite::Result<()> {
+    conn.execute(
+        "DELETE FROM feed_http_cache WHERE url = (SELECT url FROM feeds WHERE id = ?1)",
+        params![id],
+    )?;
+    conn.execute("DELETE FROM feed_health WHERE feed_id = ?1", params![id])?;
+    conn.execute("DELETE FROM filter_rules WHERE feed_id = ?1", params![id])?;
+    feed_request_options_db::delete_request_options(conn, id)?;
     conn.execute("DELETE FROM feeds WHERE id = ?1", params![id])?;
     Ok(())
 }
@@ -141,10 +225,16 @@ pub fn save_feed_processor(
     feed_id: i64,
     processor: ProcessorType,
     custom_config: Option<&str>,
+    fallback_processors: &[ProcessorType],
 ) -> rusqlite::Result<()> {
     conn.execute(
-        "INSERT OR REPLACE INTO feed_processor (feed_id, processor, custom_config) VALUES (?1, ?2, ?3)",
-        params![feed_id, processor.to_i32(), custom_config],
+        "INSERT OR REPLACE INTO feed_processor (feed_id, processor, custom_config, fallback_processors) VALUES (?1, ?2, ?3, ?4)",
+        params![
+            feed_id,
+            processor.to_i32(),
+            custom_config,
+            serde_json::to_string(fallback_processors).unwrap_or_else(|_| "[]".to_string())
+        ],
     )?;
     Ok(())
 }
@@ -0,0 +1,146 @@
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use crate::models::FeedHealth;
+
+const FEED_HEALTH_QUERY: &str =
+    "SELECT f.id, f.paused, h.last_success_at, h.last_error_at, h.last_error, h.last_http_status,
+            COALESCE(h.consecutive_failures, 0), COALESCE(h.total_fetches, 0), COALESCE(h.avg_fetch_ms, 0)
+     FROM feeds f
+     LEFT JOIN feed_health h ON f.id = h.feed_id";
+
+fn map_feed_health(row: &Row) -> rusqlite::Result<FeedHealth> {
+    Ok(FeedHealth {
+        feed_id: row.get(0)?,
+        paused: row.get(1)?,
+        last_success_at: row.get(2)?,
+        last_error_at: row.get(3)?,
+        last_error: row.get(4)?,
+        last_http_status: row.get(5)?,
+        consecutive_failures: row.get(6)?,
+        total_fetches: row.get(7)?,
+        avg_fetch_ms: row.get(8)?,
+    })
+}
+
+pub fn get_feed_health(conn: &Connection, feed_id: i64) -> rusqlite::Result<Option<FeedHealth>> {
+    conn.query_row(
+        &format!("{} WHERE f.id = ?1", FEED_HEALTH_QUERY),
+        params![feed_id],
+        map_feed_health,
+    )
+    .optional()
+}
+
+pub fn get_all_feed_health(conn: &Connection) -> rusqlite::Result<Vec<FeedHealth>> {
+    let mut stmt = conn.prepare(&format!("{} ORDER BY f.position ASC", FEED_HEALTH_QUERY))?;
+    let iter = stmt.query_map([], map_feed_health)?;
+
+    let mut health = Vec::new();
+    for item in iter {
+        health.push(item?);
+    }
+    Ok(health)
+}
+
+pub fn record_fetch_success(
+    conn: &Connection,
+    feed_id: i64,
+    http_status: Option<u16>,
+    elapsed_ms: f64,
+) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO feed_health (feed_id, last_success_at, last_http_status, consecutive_failures, total_fetches, avg_fetch_ms)
+         VALUES (?1, ?2, ?3, 0, 1, ?4)
+         ON CONFLICT(feed_id) DO UPDATE SET
+            last_success_at = excluded.last_success_at,
+            last_http_status = excluded.last_http_status,
+            consecutive_failures = 0,
+            avg_fetch_ms = avg_fetch_ms + (excluded.avg_fetch_ms - avg_fetch_ms) / (total_fetches + 1),
+            total_fetches = total_fetches + 1",
+        params![feed_id, Utc::now().to_rfc3339(), http_status, elapsed_ms],
+    )?;
+    Ok(())
+}
+
+/// Records a failed fetch and applies the auto-pause policy.
+/// Returns true when this failure caused the feed to be paused.
+pub fn record_fetch_failure(
+    conn: &Connection,
+    feed_id: i64,
+    http_status: Option<u16>,
+    error: &str,
+    elapsed_ms: f64,
+    auto_pause_after_failures: i32,
+) -> rusqlite::Result<bool> {
+    conn.execute(
+        "INSERT INTO feed_health (feed_id, last_error_at, last_error, last_http_status, consecutive_failures, total_fetches, avg_fetch_ms)
+         VALUES (?1, ?2, ?3, ?4, 1, 1, ?5)
+         ON CONFLICT(feed_id) DO UPDATE SET
+            last_error_at = excluded.last_error_at,
+            last_error = excluded.last_error,
+            last_http_status = excluded.last_http_status,
+            consecutive_failures = consecutive_failures + 1,
+            avg_fetch_ms = avg_fetch_ms + (excluded.avg_fetch_ms - avg_fetch_ms) / (total_fetches + 1),
+            total_fetches = total_fetches + 1",
+        params![feed_id, Utc::now().to_rfc3339(), error, http_status, elapsed_ms],
+    )?;
+
+    if auto_pause_after_failures <= 0 {
+        return Ok(false);
+    }
+
+    let consecutive_failures: i64 = conn.query_row(
+        "SELECT consecutive_failures FROM feed_health WHERE feed_id = ?1",
+        params![feed_id],
+        |row| row.get(0),
+    )?;
+    if consecutive_failures < auto_pause_after_failures as i64 {
+        return Ok(false);
+    }
+
+    let changed = conn.execute(
+        "UPDATE feeds SET paused = 1 WHERE id = ?1 AND paused = 0",
+        params![feed_id],
+    )?;
+    Ok(changed > 0)
+}
+
+pub fn resume_feed(conn: &Connection, feed_id: i64) -> rusqlite::Result<usize> {
+    let updated = conn.execute("UPDATE feeds SET paused = 0 WHERE id = ?1", params![feed_id])?;
+    conn.execute(
+        "UPDATE feed_health SET consecutive_failures = 0 WHERE feed_id = ?1",
+        params![feed_id],
+    )?;
+    Ok(updated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{feed_db, schema_init};
+
+    #[test]
+    fn test_auto_pause_after_consecutive_failures() {
+        let conn = schema_init::init_db(":memory:").unwrap();
+        let feed_id = feed_db::add_feed(&conn, "https://example.com/feed", None, 0).unwrap();
+
+        assert!(!record_fetch_failure(&conn, feed_id, Some(500), "HTTP 500", 100.0, 2).unwrap());
+        assert!(record_fetch_failure(&conn, feed_id, Some(500), "HTTP 500", 300.0, 2).unwrap());
+
+        let health = get_feed_health(&conn, feed_id).unwrap().unwrap();
+        assert!(health.paused);
+        assert_eq!(health.consecutive_failures, 2);
+        assert_eq!(health.total_fetches, 2);
+        assert_eq!(health.avg_fetch_ms, 200.0);
+        assert_eq!(health.last_http_status, Some(500));
+
+        resume_feed(&conn, feed_id).unwrap();
+        record_fetch_success(&conn, feed_id, Some(200), 50.0).unwrap();
+
+        let health = get_feed_health(&conn, feed_id).unwrap().unwrap();
+        assert!(!health.paused);
+        assert_eq!(health.consecutive_failures, 0);
+        assert_eq!(health.total_fetches, 3);
+        assert!(health.last_success_at.is_some());
+    }
+}
@@ -0,0 +1,71 @@
+use std::collections::HashMap;
+use chrono::Utc;
+use rusqlite::{params, Connection};
+use crate::models::RequestOptionsSummary;
+
+/// Stores the already sealed options of a feed. Returns `false` when the feed does not exist.
+pub fn save_request_options(
+    conn: &Connection,
+    feed_id: i64,
+    sealed_options: &str,
+    summary: &RequestOptionsSummary,
+) -> rusqlite::Result<bool> {
+    let summary = serde_json::to_string(summary).unwrap_or_default();
+    let changed = conn.execute(
+        "INSERT OR REPLACE INTO feed_request_options (feed_id, options, summary, updated_at)
+         SELECT ?1, ?2, ?3, ?4 WHERE EXISTS (SELECT 1 FROM feeds WHERE id = ?1)",
+        params![feed_id, sealed_options, summary, Utc::now().to_rfc3339()],
+    )?;
+    Ok(changed > 0)
+}
+
+/// Sealed options of every feed that has some, keyed by feed id.
+pub fn get_sealed_request_options(conn: &Connection) -> rusqlite::Result<HashMap<i64, String>> {
+    let mut stmt = conn.prepare("SELECT feed_id, options FROM feed_request_options")?;
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    rows.collect()
+}
+
+pub fn get_request_option_summaries(conn: &Connection) -> rusqlite::Result<HashMap<i64, RequestOptionsSummary>> {
+    let mut stmt = conn.prepare("SELECT feed_id, summary FROM feed_request_options")?;
+    let rows = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?;
+
+    let mut summaries = HashMap::new();
+    for row in rows {
+        let (feed_id, summary) = row?;
+        summaries.insert(feed_id, serde_json::from_str(&summary).unwrap_or_default());
+    }
+    Ok(summaries)
+}
+
+pub fn delete_request_options(conn: &Connection, feed_id: i64) -> rusqlite::Result<usize> {
+    conn.execute("DELETE FROM feed_request_options WHERE feed_id = ?1", params![feed_id])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{feed_db, schema_init};
+
+    #[test]
+    fn test_request_options_are_summarized_on_feeds() {
+        let conn = schema_init::init_db(":memory:").unwrap();
+        let feed_id = feed_db::add_feed(&conn, "https://example.com/private.xml", None, 0).unwrap();
+        let summary = RequestOptionsSummary {
+            header_names: vec!["X-Api-Key".to_string()],
+            auth: Some("bearer".to_string()),
+            cookie: false,
+        };
+
+        assert!(!save_request_options(&conn, feed_id + 1, "sealed", &summary).unwrap());
+        assert!(save_request_options(&conn, feed_id, "sealed", &summary).unwrap());
+
+        let feeds = feed_db::get_feeds(&conn).unwrap();
+        assert_eq!(feeds[0].request_options.as_ref(), Some(&summary));
+        assert_eq!(get_sealed_request_options(&conn).unwrap()[&feed_id], "sealed");
+
+        feed_db::delete_feed(&conn, feed_id).unwrap();
+        assert!(get_sealed_request_options(&conn).unwrap().is_empty());
+        assert_eq!(delete_request_options(&conn, feed_id).unwrap(), 0);
+    }
+}
@@ -0,0 +1,126 @@
+use chrono::Utc;
+use rusqlite::{params, Connection};
+use crate::models::{FilterAction, FilterField, FilterMatchType, FilterRule};
+
+pub fn add_filter_rule(conn: &Connection, rule: &FilterRule) -> rusqlite::Result<i64> {
+    conn.execute(
+        "INSERT INTO filter_rules (feed_id, category_id, action, match_type, field, pattern, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            rule.feed_id,
+            rule.category_id,
+            rule.action.as_str(),
+            rule.match_type.as_str(),
+            rule.field.as_str(),
+            rule.pattern,
+            Utc::now().to_rfc3339()
+        ],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// Whether the feed or category the rule is attached to exists.
+pub fn rule_target_exists(conn: &Connection, rule: &FilterRule) -> rusqlite::Result<bool> {
+    let (table, id) = match (rule.feed_id, rule.category_id) {
+        (Some(feed_id), _) => ("feeds", feed_id),
+        (None, Some(category_id)) => ("categories", category_id),
+        (None, None) => return Ok(false),
+    };
+    conn.query_row(
+        &format!("SELECT EXISTS(SELECT 1 FROM {} WHERE id = ?1)", table),
+        params![id],
+        |row| row.get(0),
+    )
+}
+
+pub fn get_filter_rules(conn: &Connection) -> rusqlite::Result<Vec<FilterRule>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, feed_id, category_id, action, match_type, field, pattern FROM filter_rules ORDER BY id ASC",
+    )?;
+    let iter = stmt.query_map([], |row| {
+        Ok(FilterRule {
+            id: Some(row.get(0)?),
+            feed_id: row.get(1)?,
+            category_id: row.get(2)?,
+            action: FilterAction::from_db(&row.get::<_, String>(3)?),
+            match_type: FilterMatchType::from_db(&row.get::<_, String>(4)?),
+            field: FilterField::from_db(&row.get::<_, String>(5)?),
+            pattern: row.get(6)?,
+        })
+    })?;
+
+    let mut rules = Vec::new();
+    for rule in iter {
+        rules.push(rule?);
+    }
+    Ok(rules)
+}
+
+pub fn delete_filter_rule(conn: &Connection, id: i64) -> rusqlite::Result<usize> {
+    conn.execute("DELETE FROM filter_rules WHERE id = ?1", params![id])
+}
+
+/// Rules that apply to a feed: its own plus those of its category.
+pub fn rules_for_feed(rules: &[FilterRule], feed_id: Option<i64>, category_id: Option<i64>) -> Vec<FilterRule> {
+    rules
+        .iter()
+        .filter(|r| {
+            (feed_id.is_some() && r.feed_id == feed_id)
+                || (category_id.is_some() && r.category_id == category_id)
+        })
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{category_db, feed_db, schema_init};
+
+    #[test]
+    fn test_filter_rules_attach_to_feeds_and_categories() {
+        let conn = schema_init::init_db(":memory:").unwrap();
+        let feed_id = feed_db::add_feed(&conn, "https://example.com/feed", None, 0).unwrap();
+        let category_id = category_db::add_category(&conn, "News").unwrap();
+        category_db::update_feed_category(&conn, feed_id, Some(category_id)).unwrap();
+
+        let rule = FilterRule {
+            id: None,
+            feed_id: Some(feed_id),
+            category_id: None,
+            action: FilterAction::Exclude,
+            match_type: FilterMatchType::Keyword,
+            field: FilterField::Title,
+            pattern: "sponsored".to_string(),
+        };
+        assert!(rule_target_exists(&conn, &rule).unwrap());
+        assert!(!rule_target_exists(&conn, &FilterRule { feed_id: Some(feed_id + 1), ..rule.clone() }).unwrap());
+        assert!(!rule_target_exists(&conn, &FilterRule { feed_id: None, category_id: Some(category_id + 1), ..rule.clone() }).unwrap());
+        add_filter_rule(&conn, &rule).unwrap();
+        add_filter_rule(
+            &conn,
+            &FilterRule {
+                feed_id: None,
+                category_id: Some(category_id),
+                action: FilterAction::Include,
+                match_type: FilterMatchType::Regex,
+                field: FilterField::Tags,
+                pattern: "(?i)rust".to_string(),
+                ..rule
+            },
+        )
+        .unwrap();
+
+        let feeds = feed_db::get_feeds(&conn).unwrap();
+        assert_eq!(feeds[0].filter_rules.len(), 1);
+        assert_eq!(feeds[0].filter_rules[0].action, FilterAction::Exclude);
+
+        let categories = category_db::get_categories(&conn).unwrap();
+        assert_eq!(categories[0].filter_rules[0].field, FilterField::Tags);
+
+        let rules = get_filter_rules(&conn).unwrap();
+        assert_eq!(rules_for_feed(&rules, Some(feed_id), Some(category_id)).len(), 2);
+
+        feed_db::delete_feed(&conn, feed_id).unwrap();
+        assert_eq!(get_filter_rules(&conn).unwrap().len(), 1);
+    }
+}
@@ -227,3 +227,193 @@ pub fn migrate_email_config_smtp_username(conn: &Connection) -> Result<(), Error
 
     Ok(())
 }
+
+pub fn migrate_feed_paused(conn: &Connection) -> Result<(), Error> {
+    if !has_column(conn, "feeds", "paused") {
+        conn.execute(
+            "ALTER TABLE feeds ADD COLUMN paused BOOLEAN NOT NULL DEFAULT 0",
+            [],
+        )?;
+    }
+
+    Ok(())
+}
+
+pub fn migrate_general_config_auto_pause(conn: &Connection) -> Result<(), Error> {
+    if !has_column(conn, "general_config", "auto_pause_after_failures") {
+        conn.execute(
+            "ALTER TABLE general_config ADD COLUMN auto_pause_after_failures INTEGER NOT NULL DEFAULT 0",
+            [],
+        )?;
+    }
+
+    Ok(())
+}
+
+pub fn migrate_schedule_article_selection(conn: &Connection) -> Result<(), Error> {
+    if !has_column(conn, "schedules", "article_selection") {
+        conn.execute(
+            "ALTER TABLE schedules ADD COLUMN article_selection TEXT NOT NULL DEFAULT 'window'",
+            [],
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Existing installs keep digests as they were: deduplication stays off until
+/// enabled. New databases have it on (see `schema_init`).
+pub fn migrate_general_config_dedup(conn: &Connection) -> Result<(), Error> {
+    if !has_column(conn, "general_config", "dedup_articles") {
+        conn.execute(
+            "ALTER TABLE general_config ADD COLUMN dedup_articles BOOLEAN NOT NULL DEFAULT 0",
+            [],
+        )?;
+        // Without a saved row the code defaults would apply instead.
+        conn.execute("INSERT OR IGNORE INTO general_config (id) VALUES (1)", [])?;
+    }
+    if !has_column(conn, "general_config", "dedup_title_similarity") {
+        conn.execute(
+            "ALTER TABLE general_config ADD COLUMN dedup_title_similarity REAL NOT NULL DEFAULT 0",
+            [],
+        )?;
+    }
+
+    Ok(())
+}
+
+pub fn migrate_feed_article_limit(conn: &Connection) -> Result<(), Error> {
+    if !has_column(conn, "feeds", "max_articles") {
+        conn.execute(
+            "ALTER TABLE feeds ADD COLUMN max_articles INTEGER NOT NULL DEFAULT 0",
+            [],
+        )?;
+    }
+    if !has_column(conn, "feeds", "article_order") {
+        conn.execute(
+            "ALTER TABLE feeds ADD COLUMN article_order TEXT NOT NULL DEFAULT 'newest'",
+            [],
+        )?;
+    }
+
+    Ok(())
+}
+
+pub fn migrate_feed_source_type(conn: &Connection) -> Result<(), Error> {
+    if !has_column(conn, "feeds", "source_type") {
+        conn.execute(
+            "ALTER TABLE feeds ADD COLUMN source_type TEXT NOT NULL DEFAULT 'feed'",
+            [],
+        )?;
+    }
+    if !has_column(conn, "feeds", "scrape_config") {
+        conn.execute("ALTER TABLE feeds ADD COLUMN scrape_config TEXT", [])?;
+    }
+
+    Ok(())
+}
+
+pub fn migrate_general_config_http(conn: &Connection) -> Result<(), Error> {
+    let columns = [
+        ("http_user_agent", "TEXT NOT NULL DEFAULT ''"),
+        ("http_proxy", "TEXT NOT NULL DEFAULT ''"),
+        ("http_connect_timeout_seconds", "INTEGER NOT NULL DEFAULT 10"),
+        ("http_read_timeout_seconds", "INTEGER NOT NULL DEFAULT 45"),
+        ("http_max_response_mb", "INTEGER NOT NULL DEFAULT 20"),
+        ("http_max_redirects", "INTEGER NOT NULL DEFAULT 10"),
+        ("http_max_retries", "INTEGER NOT NULL DEFAULT 2"),
+    ];
+    for (column, definition) in columns {
+        if !has_column(conn, "general_config", column) {
+            conn.execute(
+                &format!("ALTER TABLE general_config ADD COLUMN {} {}", column, definition),
+                [],
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn migrate_domain_override_fallbacks(conn: &Connection) -> Result<(), Error> {
+    if !has_column(conn, "domain_override", "fallback_urls") {
+        conn.execute(
+            "ALTER TABLE domain_override ADD COLUMN fallback_urls TEXT NOT NULL DEFAULT '[]'",
+            [],
+        )?;
+    }
+    if !has_column(conn, "domain_override", "min_content_words") {
+        conn.execute(
+            "ALTER TABLE domain_override ADD COLUMN min_content_words INTEGER NOT NULL DEFAULT 0",
+            [],
+        )?;
+    }
+    if !has_column(conn, "domain_override", "next_page_selector") {
+        conn.execute("ALTER TABLE domain_override ADD COLUMN next_page_selector TEXT", [])?;
+    }
+
+    Ok(())
+}
+
+pub fn migrate_general_config_article_cache(conn: &Connection) -> Result<(), Error> {
+    if !has_column(conn, "general_config", "article_cache_ttl_hours") {
+        conn.execute(
+            "ALTER TABLE general_config ADD COLUMN article_cache_ttl_hours INTEGER NOT NULL DEFAULT 24",
+            [],
+        )?;
+    }
+
+    Ok(())
+}
+
+pub fn migrate_processor_fallback_chain(conn: &Connection) -> Result<(), Error> {
+    for table in ["feed_processor", "domain_override"] {
+        if !has_column(conn, table, "fallback_processors") {
+            conn.execute(
+                &format!("ALTER TABLE {} ADD COLUMN fallback_processors TEXT NOT NULL DEFAULT '[]'", table),
+                [],
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn migrate_link_canonicalization(conn: &Connection) -> Result<(), Error> {
+    // NULL keeps the built-in tracking parameter list.
+    if !has_column(conn, "general_config", "tracking_params") {
+        conn.execute("ALTER TABLE general_config ADD COLUMN tracking_params TEXT", [])?;
+    }
+    if !has_column(conn, "article_cache", "canonical_url") {
+        conn.execute("ALTER TABLE article_cache ADD COLUMN canonical_url TEXT", [])?;
+    }
+
+    Ok(())
+}
+
+pub fn migrate_general_config_link_endnotes(conn: &Connection) -> Result<(), Error> {
+    if !has_column(conn, "general_config", "link_endnotes") {
+        conn.execute(
+            "ALTER TABLE general_config ADD COLUMN link_endnotes INTEGER NOT NULL DEFAULT 0",
+            [],
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Existing installs stay on the basic profile so their digests don't suddenly
+/// carry tables and MathML; extended is opt-in for them and the default for
+/// new databases.
+pub fn migrate_general_config_html_profile(conn: &Connection) -> Result<(), Error> {
+    if !has_column(conn, "general_config", "html_profile") {
+        conn.execute(
+            "ALTER TABLE general_config ADD COLUMN html_profile TEXT NOT NULL DEFAULT 'basic'",
+            [],
+        )?;
+        // Without a saved row the code defaults would apply instead.
+        conn.execute("INSERT OR IGNORE INTO general_config (id) VALUES (1)", [])?;
+    }
+
+    Ok(())
+}
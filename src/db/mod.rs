@@ -1,36 +1,43 @@
 use chrono::Utc;
 use rusqlite::{Connection, Result, Transaction, params};
 
-use crate::models::{CoverTextColor, CoverTextPosition, CoverTextSize, DomainOverride, EmailConfig, GeneralConfig, ProcessorType, ReadItLaterArticle, Schedule};
+use crate::models::{ArticleSelection, CoverTextColor, CoverTextPosition, CoverTextSize, ContentProcessor, DomainOverride, EmailConfig, GeneralConfig, HtmlProfile, ProcessorType, ReadItLaterArticle, Schedule, default_tracking_params};
 
+pub mod article_cache_db;
 pub mod category_db;
+pub mod delivery_db;
+pub mod feed_cache_db;
 pub mod feed_db;
+pub mod feed_health_db;
+pub mod feed_request_options_db;
+pub mod filter_rule_db;
 mod migration;
 pub mod schema_init;
 
-pub fn add_schedule(
-    conn: &Connection,
-    cron_expression: &str,
-    schedule_type: &str,
-    timezone: &str,
-    category_ids: &[i64],
-    override_to_email: Option<&str>,
-    fetch_since_hours_override: Option<i32>,
-) -> Result<()> {
+pub fn add_schedule(conn: &Connection, schedule: &Schedule) -> Result<()> {
     let tx = conn.unchecked_transaction()?;
     tx.execute(
-        "INSERT INTO schedules (cron_expression, active, schedule_type, timezone, override_to_email, fetch_since_hours_override, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-        params![cron_expression, true, schedule_type, timezone, override_to_email, fetch_since_hours_override, Utc::now().to_rfc3339()],
+        "INSERT INTO schedules (cron_expression, active, schedule_type, timezone, override_to_email, fetch_since_hours_override, article_selection, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            schedule.cron_expression,
+            schedule.active,
+            schedule.schedule_type,
+            schedule.timezone,
+            schedule.override_to_email,
+            schedule.fetch_since_hours_override,
+            schedule.article_selection.as_str(),
+            Utc::now().to_rfc3339()
+        ],
     )?;
     let schedule_id = tx.last_insert_rowid();
-    save_schedule_categories(&tx, schedule_id, category_ids)?;
+    save_schedule_categories(&tx, schedule_id, &schedule.category_ids)?;
     tx.commit()?;
     Ok(())
 }
 
 pub fn get_schedules(conn: &Connection) -> Result<Vec<Schedule>> {
     let mut stmt = conn.prepare(
-        "SELECT id, cron_expression, active, schedule_type, timezone, override_to_email, fetch_since_hours_override FROM schedules",
+        "SELECT id, cron_expression, active, schedule_type, timezone, override_to_email, fetch_since_hours_override, article_selection FROM schedules",
     )?;
     let schedule_iter = stmt.query_map([], |row| {
         let id: i64 = row.get(0)?;
@@ -43,6 +50,7 @@ pub fn get_schedules(conn: &Connection) -> Result<Vec<Schedule>> {
             category_ids: get_schedule_category_ids(conn, id)?,
             override_to_email: row.get(5)?,
             fetch_since_hours_override: row.get(6)?,
+            article_selection: ArticleSelection::from_db(&row.get::<_, String>(7)?),
         })
     })?;
 
@@ -53,22 +61,21 @@ pub fn get_schedules(conn: &Connection) -> Result<Vec<Schedule>> {
     Ok(schedules)
 }
 
-pub fn update_schedule(
-    conn: &Connection,
-    id: i64,
-    cron_expression: &str,
-    schedule_type: &str,
-    timezone: &str,
-    category_ids: &[i64],
-    override_to_email: Option<&str>,
-    fetch_since_hours_override: Option<i32>,
-) -> Result<()> {
+pub fn update_schedule(conn: &Connection, id: i64, schedule: &Schedule) -> Result<()> {
     let tx = conn.unchecked_transaction()?;
     tx.execute(
-        "UPDATE schedules SET cron_expression = ?1, schedule_type = ?2, timezone = ?3, override_to_email = ?4, fetch_since_hours_override = ?5, category_id = NULL WHERE id = ?6",
-        params![cron_expression, schedule_type, timezone, override_to_email, fetch_since_hours_override, id],
+        "UPDATE schedules SET cron_expression = ?1, schedule_type = ?2, timezone = ?3, override_to_email = ?4, fetch_since_hours_override = ?5, article_selection = ?6, category_id = NULL WHERE id = ?7",
+        params![
+            schedule.cron_expression,
+            schedule.schedule_type,
+            schedule.timezone,
+            schedule.override_to_email,
+            schedule.fetch_since_hours_override,
+            schedule.article_selection.as_str(),
+            id
+        ],
     )?;
-    save_schedule_categories(&tx, id, category_ids)?;
+    save_schedule_categories(&tx, id, &schedule.category_ids)?;
     tx.commit()?;
     Ok(())
 }
@@ -78,6 +85,7 @@ pub fn delete_schedule(conn: &Connection, id: i64) -> Result<()> {
         "DELETE FROM schedule_category WHERE schedule_id = ?1",
         params![id],
     )?;
+    delivery_db::delete_schedule_deliveries(conn, id)?;
     conn.execute("DELETE FROM schedules WHERE id = ?1", params![id])?;
     Ok(())
 }
@@ -218,7 +226,7 @@ pub fn mark_articles_as_read(conn: &Connection, ids: &[i64]) -> Result<()> {
 }
 
 pub fn get_general_config(conn: &Connection) -> Result<GeneralConfig> {
-    let mut stmt = conn.prepare("SELECT fetch_since_hours, image_timeout_seconds, cover_text_enabled, cover_text_color, cover_text_position, cover_text_size FROM general_config WHERE id = 1")?;
+    let mut stmt = conn.prepare("SELECT fetch_since_hours, image_timeout_seconds, cover_text_enabled, cover_text_color, cover_text_position, cover_text_size, auto_pause_after_failures, dedup_articles, dedup_title_similarity, http_user_agent, http_proxy, http_connect_timeout_seconds, http_read_timeout_seconds, http_max_response_mb, http_max_redirects, http_max_retries, article_cache_ttl_hours, tracking_params, link_endnotes, html_profile FROM general_config WHERE id = 1")?;
     let mut config_iter = stmt.query_map([], |row| {
         let cover_text_color = row.get::<_, String>(3).unwrap_or_else(|_| "white".to_string());
         let cover_text_position = row
@@ -233,6 +241,25 @@ pub fn get_general_config(conn: &Connection) -> Result<GeneralConfig> {
             cover_text_color: CoverTextColor::from_db(&cover_text_color),
             cover_text_position: CoverTextPosition::from_db(&cover_text_position),
             cover_text_size: CoverTextSize::from_db(&cover_text_size),
+            auto_pause_after_failures: row.get(6).unwrap_or(0),
+            dedup_articles: row.get(7).unwrap_or(true),
+            dedup_title_similarity: row.get(8).unwrap_or(0.0),
+            http_user_agent: row.get(9).unwrap_or_default(),
+            http_proxy: row.get(10).unwrap_or_default(),
+            http_connect_timeout_seconds: row.get(11).unwrap_or(10),
+            http_read_timeout_seconds: row.get(12).unwrap_or(45),
+            http_max_response_mb: row.get(13).unwrap_or(20),
+            http_max_redirects: row.get(14).unwrap_or(10),
+            http_max_retries: row.get(15).unwrap_or(2),
+            article_cache_ttl_hours: row.get(16).unwrap_or(24),
+            tracking_params: row
+                .get::<_, Option<String>>(17)
+                .ok()
+                .flatten()
+                .and_then(|p| serde_json::from_str(&p).ok())
+                .unwrap_or_else(default_tracking_params),
+            link_endnotes: row.get(18).unwrap_or(false),
+            html_profile: HtmlProfile::from_db(&row.get::<_, String>(19).unwrap_or_default()),
         })
     })?;
 
@@ -246,14 +273,28 @@ pub fn get_general_config(conn: &Connection) -> Result<GeneralConfig> {
             cover_text_color: CoverTextColor::default(),
             cover_text_position: CoverTextPosition::default(),
             cover_text_size: CoverTextSize::default(),
+            auto_pause_after_failures: 0,
+            dedup_articles: true,
+            dedup_title_similarity: 0.0,
+            http_user_agent: String::new(),
+            http_proxy: String::new(),
+            http_connect_timeout_seconds: 10,
+            http_read_timeout_seconds: 45,
+            http_max_response_mb: 20,
+            http_max_redirects: 10,
+            http_max_retries: 2,
+            article_cache_ttl_hours: 24,
+            tracking_params: default_tracking_params(),
+            link_endnotes: false,
+            html_profile: HtmlProfile::default(),
         })
     }
 }
 
 pub fn update_general_config(conn: &Connection, config: &GeneralConfig) -> Result<()> {
     conn.execute(
-        "INSERT OR REPLACE INTO general_config (id, fetch_since_hours, image_timeout_seconds, cover_text_enabled, cover_text_color, cover_text_position, cover_text_size) VALUES (1, ?1, ?2, ?3, ?4, ?5, ?6)",
-        params![config.fetch_since_hours, config.image_timeout_seconds, config.cover_text_enabled, config.cover_text_color.as_str(), config.cover_text_position.as_str(), config.cover_text_size.as_str()],
+        "INSERT OR REPLACE INTO general_config (id, fetch_since_hours, image_timeout_seconds, cover_text_enabled, cover_text_color, cover_text_position, cover_text_size, auto_pause_after_failures, dedup_articles, dedup_title_similarity, http_user_agent, http_proxy, http_connect_timeout_seconds, http_read_timeout_seconds, http_max_response_mb, http_max_redirects, http_max_retries, article_cache_ttl_hours, tracking_params, link_endnotes, html_profile) VALUES (1, ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20)",
+        params![config.fetch_since_hours, config.image_timeout_seconds, config.cover_text_enabled, config.cover_text_color.as_str(), config.cover_text_position.as_str(), config.cover_text_size.as_str(), config.auto_pause_after_failures, config.dedup_articles, config.dedup_title_similarity, config.http_user_agent, config.http_proxy, config.http_connect_timeout_seconds, config.http_read_timeout_seconds, config.http_max_response_mb, config.http_max_redirects, config.http_max_retries, config.article_cache_ttl_hours, serde_json::to_string(&config.tracking_params).unwrap_or_default(), config.link_endnotes, config.html_profile.as_str()],
     )?;
     Ok(())
 }
@@ -261,26 +302,44 @@ pub fn update_general_config(conn: &Connection, config: &GeneralConfig) -> Resul
 pub fn add_domain_override(
     conn: &Connection,
     domain: &str,
-    processor: ProcessorType,
-    custom_config: Option<&str>,
+    processor: &ContentProcessor,
+    fallback_urls: &[String],
+    min_content_words: usize,
+    next_page_selector: Option<&str>,
 ) -> Result<i64> {
     conn.execute(
-        "INSERT OR REPLACE INTO domain_override (domain, processor, custom_config, created_at) VALUES (?1, ?2, ?3, ?4)",
-        params![domain.to_lowercase(), processor.to_i32(), custom_config, Utc::now().to_rfc3339()],
+        "INSERT OR REPLACE INTO domain_override (domain, processor, custom_config, created_at, fallback_urls, min_content_words, next_page_selector, fallback_processors) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            domain,
+            processor.processor.to_i32(),
+            processor.custom_config,
+            Utc::now().to_rfc3339(),
+            serde_json::to_string(fallback_urls).unwrap_or_else(|_| "[]".to_string()),
+            min_content_words as i64,
+            next_page_selector,
+            serde_json::to_string(&processor.fallback_processors).unwrap_or_else(|_| "[]".to_string())
+        ],
     )?;
     Ok(conn.last_insert_rowid())
 }
 
 pub fn get_domain_overrides(conn: &Connection) -> Result<Vec<DomainOverride>> {
-    let mut stmt = conn.prepare("SELECT id, domain, processor, custom_config, created_at FROM domain_override ORDER BY created_at DESC")?;
+    let mut stmt = conn.prepare("SELECT id, domain, processor, custom_config, created_at, fallback_urls, min_content_words, next_page_selector, fallback_processors FROM domain_override ORDER BY created_at DESC")?;
     let iter = stmt.query_map([], |row| {
         let processor_int: i32 = row.get(2)?;
+        let fallback_urls: String = row.get(5)?;
+        let min_content_words: i64 = row.get(6)?;
+        let fallback_processors: String = row.get(8)?;
         Ok(DomainOverride {
             id: Some(row.get(0)?),
             domain: row.get(1)?,
             processor: ProcessorType::from_i32(processor_int),
             custom_config: row.get(3)?,
+            fallback_processors: serde_json::from_str(&fallback_processors).unwrap_or_default(),
             created_at: row.get(4)?,
+            fallback_urls: serde_json::from_str(&fallback_urls).unwrap_or_default(),
+            min_content_words: min_content_words.max(0) as usize,
+            next_page_selector: row.get(7)?,
         })
     })?;
 
@@ -309,13 +368,42 @@ mod tests {
                 cover_text_enabled BOOLEAN NOT NULL DEFAULT 0,
                 cover_text_color TEXT NOT NULL DEFAULT 'white',
                 cover_text_position TEXT NOT NULL DEFAULT 'bottom-right',
-                cover_text_size TEXT NOT NULL DEFAULT 'small'
+                cover_text_size TEXT NOT NULL DEFAULT 'small',
+                auto_pause_after_failures INTEGER NOT NULL DEFAULT 0,
+                dedup_articles BOOLEAN NOT NULL DEFAULT 1,
+                dedup_title_similarity REAL NOT NULL DEFAULT 0,
+                http_user_agent TEXT NOT NULL DEFAULT '',
+                http_proxy TEXT NOT NULL DEFAULT '',
+                http_connect_timeout_seconds INTEGER NOT NULL DEFAULT 10,
+                http_read_timeout_seconds INTEGER NOT NULL DEFAULT 45,
+                http_max_response_mb INTEGER NOT NULL DEFAULT 20,
+                http_max_redirects INTEGER NOT NULL DEFAULT 10,
+                http_max_retries INTEGER NOT NULL DEFAULT 2,
+                article_cache_ttl_hours INTEGER NOT NULL DEFAULT 24,
+                tracking_params TEXT,
+                link_endnotes INTEGER NOT NULL DEFAULT 0,
+                html_profile TEXT NOT NULL DEFAULT 'extended'
             )",
             [],
         )
         .unwrap();
     }
 
+    #[test]
+    fn upgraded_installs_keep_the_basic_html_profile() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE general_config (id INTEGER PRIMARY KEY CHECK (id = 1), fetch_since_hours INTEGER NOT NULL DEFAULT 24)",
+            [],
+        )
+        .unwrap();
+        migration::migrate_general_config_html_profile(&conn).unwrap();
+        let profile: String = conn
+            .query_row("SELECT html_profile FROM general_config WHERE id = 1", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(HtmlProfile::from_db(&profile), HtmlProfile::Basic);
+    }
+
     #[test]
     fn test_update_general_config() {
         let conn = Connection::open_in_memory().unwrap();
@@ -329,6 +417,20 @@ mod tests {
             cover_text_color: CoverTextColor::Black,
             cover_text_position: CoverTextPosition::TopLeft,
             cover_text_size: CoverTextSize::Large,
+            auto_pause_after_failures: 5,
+            dedup_articles: false,
+            dedup_title_similarity: 0.75,
+            http_user_agent: "rsspub-test".to_string(),
+            http_proxy: "socks5h://127.0.0.1:1080".to_string(),
+            http_connect_timeout_seconds: 5,
+            http_read_timeout_seconds: 30,
+            http_max_response_mb: 8,
+            http_max_redirects: 3,
+            http_max_retries: 4,
+            article_cache_ttl_hours: 6,
+            tracking_params: vec!["utm_*".to_string(), "share".to_string()],
+            link_endnotes: true,
+            html_profile: HtmlProfile::Basic,
         };
 
         update_general_config(&conn, &new_config).unwrap();
@@ -340,6 +442,20 @@ mod tests {
         assert_eq!(fetched_config.cover_text_color, CoverTextColor::Black);
         assert_eq!(fetched_config.cover_text_position, CoverTextPosition::TopLeft);
         assert_eq!(fetched_config.cover_text_size, CoverTextSize::Large);
+        assert_eq!(fetched_config.auto_pause_after_failures, 5);
+        assert!(!fetched_config.dedup_articles);
+        assert_eq!(fetched_config.dedup_title_similarity, 0.75);
+        assert_eq!(fetched_config.http_user_agent, "rsspub-test");
+        assert_eq!(fetched_config.http_proxy, "socks5h://127.0.0.1:1080");
+        assert_eq!(fetched_config.http_connect_timeout_seconds, 5);
+        assert_eq!(fetched_config.http_read_timeout_seconds, 30);
+        assert_eq!(fetched_config.http_max_response_mb, 8);
+        assert_eq!(fetched_config.http_max_redirects, 3);
+        assert_eq!(fetched_config.http_max_retries, 4);
+        assert_eq!(fetched_config.article_cache_ttl_hours, 6);
+        assert_eq!(fetched_config.tracking_params, ["utm_*", "share"]);
+        assert!(fetched_config.link_endnotes);
+        assert_eq!(fetched_config.html_profile, HtmlProfile::Basic);
 
         // Update again
         let updated_config = GeneralConfig {
@@ -349,6 +465,20 @@ mod tests {
             cover_text_color: CoverTextColor::White,
             cover_text_position: CoverTextPosition::BottomRight,
             cover_text_size: CoverTextSize::Small,
+            auto_pause_after_failures: 0,
+            dedup_articles: true,
+            dedup_title_similarity: 0.0,
+            http_user_agent: String::new(),
+            http_proxy: String::new(),
+            http_connect_timeout_seconds: 10,
+            http_read_timeout_seconds: 45,
+            http_max_response_mb: 0,
+            http_max_redirects: 10,
+            http_max_retries: 0,
+            article_cache_ttl_hours: 0,
+            tracking_params: Vec::new(),
+            link_endnotes: false,
+            html_profile: HtmlProfile::Extended,
         };
         update_general_config(&conn, &updated_config).unwrap();
 
@@ -359,5 +489,10 @@ mod tests {
         assert_eq!(fetched_config_2.cover_text_color, CoverTextColor::White);
         assert_eq!(fetched_config_2.cover_text_position, CoverTextPosition::BottomRight);
         assert_eq!(fetched_config_2.cover_text_size, CoverTextSize::Small);
+        assert_eq!(fetched_config_2.auto_pause_after_failures, 0);
+        assert!(fetched_config_2.dedup_articles);
+        assert!(fetched_config_2.http_proxy.is_empty());
+        assert_eq!(fetched_config_2.http_max_response_mb, 0);
+        assert!(fetched_config_2.tracking_params.is_empty());
     }
 }
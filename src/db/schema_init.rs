@@ -11,6 +11,11 @@ pub fn init_db(path: &str) -> rusqlite::Result<Connection> {
             name TEXT,
             concurrency_limit INTEGER NOT NULL DEFAULT 0,
             position INTEGER NOT NULL DEFAULT 0,
+            paused BOOLEAN NOT NULL DEFAULT 0,
+            max_articles INTEGER NOT NULL DEFAULT 0,
+            article_order TEXT NOT NULL DEFAULT 'newest',
+            source_type TEXT NOT NULL DEFAULT 'feed',
+            scrape_config TEXT,
             created_at TEXT NOT NULL
         )",
         [],
@@ -26,7 +31,8 @@ pub fn init_db(path: &str) -> rusqlite::Result<Connection> {
             created_at TEXT NOT NULL,
             category_id INTEGER,
             override_to_email TEXT,
-            fetch_since_hours_override INTEGER
+            fetch_since_hours_override INTEGER,
+            article_selection TEXT NOT NULL DEFAULT 'window'
         )",
         [],
     )?;
@@ -110,7 +116,21 @@ pub fn init_db(path: &str) -> rusqlite::Result<Connection> {
             cover_text_enabled BOOLEAN NOT NULL DEFAULT 0,
             cover_text_color TEXT NOT NULL DEFAULT 'white',
             cover_text_position TEXT NOT NULL DEFAULT 'bottom-right',
-            cover_text_size TEXT NOT NULL DEFAULT 'small'
+            cover_text_size TEXT NOT NULL DEFAULT 'small',
+            auto_pause_after_failures INTEGER NOT NULL DEFAULT 0,
+            dedup_articles BOOLEAN NOT NULL DEFAULT 1,
+            dedup_title_similarity REAL NOT NULL DEFAULT 0,
+            http_user_agent TEXT NOT NULL DEFAULT '',
+            http_proxy TEXT NOT NULL DEFAULT '',
+            http_connect_timeout_seconds INTEGER NOT NULL DEFAULT 10,
+            http_read_timeout_seconds INTEGER NOT NULL DEFAULT 45,
+            http_max_response_mb INTEGER NOT NULL DEFAULT 20,
+            http_max_redirects INTEGER NOT NULL DEFAULT 10,
+            http_max_retries INTEGER NOT NULL DEFAULT 2,
+            article_cache_ttl_hours INTEGER NOT NULL DEFAULT 24,
+            tracking_params TEXT,
+            link_endnotes INTEGER NOT NULL DEFAULT 0,
+            html_profile TEXT NOT NULL DEFAULT 'extended'
         )",
         [],
     )?;
@@ -120,6 +140,7 @@ pub fn init_db(path: &str) -> rusqlite::Result<Connection> {
             feed_id INTEGER PRIMARY KEY,
             processor INTEGER NOT NULL DEFAULT 1,
             custom_config TEXT,
+            fallback_processors TEXT NOT NULL DEFAULT '[]',
             FOREIGN KEY (feed_id) REFERENCES feeds(id) ON DELETE CASCADE
         )",
         [],
@@ -130,7 +151,99 @@ pub fn init_db(path: &str) -> rusqlite::Result<Connection> {
             domain TEXT NOT NULL UNIQUE,
             processor INTEGER NOT NULL,
             custom_config TEXT,
-            created_at TEXT NOT NULL
+            created_at TEXT NOT NULL,
+            fallback_urls TEXT NOT NULL DEFAULT '[]',
+            min_content_words INTEGER NOT NULL DEFAULT 0,
+            next_page_selector TEXT,
+            fallback_processors TEXT NOT NULL DEFAULT '[]'
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS feed_http_cache (
+            url TEXT PRIMARY KEY,
+            etag TEXT,
+            last_modified TEXT,
+            parsed_feed TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS article_cache (
+            url TEXT NOT NULL,
+            config_hash TEXT NOT NULL,
+            title TEXT NOT NULL,
+            content TEXT NOT NULL,
+            source_url TEXT NOT NULL,
+            pages INTEGER NOT NULL DEFAULT 1,
+            author TEXT,
+            published TEXT,
+            lead_image TEXT,
+            canonical_url TEXT,
+            created_at TEXT NOT NULL,
+            PRIMARY KEY (url, config_hash)
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS feed_health (
+            feed_id INTEGER PRIMARY KEY,
+            last_success_at TEXT,
+            last_error_at TEXT,
+            last_error TEXT,
+            last_http_status INTEGER,
+            consecutive_failures INTEGER NOT NULL DEFAULT 0,
+            total_fetches INTEGER NOT NULL DEFAULT 0,
+            avg_fetch_ms REAL NOT NULL DEFAULT 0,
+            FOREIGN KEY (feed_id) REFERENCES feeds(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS filter_rules (
+            id INTEGER PRIMARY KEY,
+            feed_id INTEGER,
+            category_id INTEGER,
+            action TEXT NOT NULL,
+            match_type TEXT NOT NULL DEFAULT 'keyword',
+            field TEXT NOT NULL DEFAULT 'title',
+            pattern TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (feed_id) REFERENCES feeds(id) ON DELETE CASCADE,
+            FOREIGN KEY (category_id) REFERENCES categories(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS delivered_articles (
+            schedule_id INTEGER NOT NULL,
+            entry_id TEXT NOT NULL,
+            link TEXT,
+            delivered_at TEXT NOT NULL,
+            PRIMARY KEY (schedule_id, entry_id),
+            FOREIGN KEY (schedule_id) REFERENCES schedules(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_delivered_articles_link ON delivered_articles(schedule_id, link)",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS feed_request_options (
+            feed_id INTEGER PRIMARY KEY,
+            options TEXT NOT NULL,
+            summary TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            FOREIGN KEY (feed_id) REFERENCES feeds(id) ON DELETE CASCADE
         )",
         [],
     )?;
@@ -144,5 +257,18 @@ pub fn init_db(path: &str) -> rusqlite::Result<Connection> {
     migration::migrate_schedule_categories(&conn)?;
     migration::migrate_general_config_cover_text(&conn)?;
     migration::migrate_email_config_smtp_username(&conn)?;
+    migration::migrate_feed_paused(&conn)?;
+    migration::migrate_general_config_auto_pause(&conn)?;
+    migration::migrate_schedule_article_selection(&conn)?;
+    migration::migrate_general_config_dedup(&conn)?;
+    migration::migrate_feed_article_limit(&conn)?;
+    migration::migrate_feed_source_type(&conn)?;
+    migration::migrate_general_config_http(&conn)?;
+    migration::migrate_domain_override_fallbacks(&conn)?;
+    migration::migrate_general_config_article_cache(&conn)?;
+    migration::migrate_processor_fallback_chain(&conn)?;
+    migration::migrate_link_canonicalization(&conn)?;
+    migration::migrate_general_config_link_endnotes(&conn)?;
+    migration::migrate_general_config_html_profile(&conn)?;
     Ok(conn)
 }
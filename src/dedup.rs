@@ -0,0 +1,137 @@
+use std::collections::{HashMap, HashSet};
+use tracing::info;
+use crate::feed::Article;
+use crate::util::urls;
+
+/// Collapses articles that point at the same story. Links are compared after
+/// normalization; when `title_similarity` is above 0, titles whose word overlap
+/// (Jaccard index) reaches it are treated as the same story as well.
+/// The copy from the feed with the lowest `position` is kept and the other
+/// sources are listed in its `also_in`. Articles that did not come from a feed
+/// entry (such as feed error pages) are never merged.
+pub fn dedup_articles(articles: Vec<Article>, title_similarity: f64) -> Vec<Article> {
+    let mut ordered: Vec<(usize, Article)> = articles.into_iter().enumerate().collect();
+    ordered.sort_by_key(|(idx, a)| (a.article_source.position, *idx));
+
+    let mut kept: Vec<(usize, Article)> = Vec::new();
+    let mut kept_titles: Vec<HashSet<String>> = Vec::new();
+    let mut by_url: HashMap<String, usize> = HashMap::new();
+    let mut removed = 0;
+
+    for (idx, article) in ordered {
+        if article.entry_id.is_empty() {
+            kept_titles.push(HashSet::new());
+            kept.push((idx, article));
+            continue;
+        }
+
+        let url_key = urls::normalize_url(&article.link);
+        let title_words = title_words(&article.title);
+
+        let duplicate_of = url_key
+            .as_ref()
+            .and_then(|key| by_url.get(key).copied())
+            .or_else(|| {
+                if title_similarity <= 0.0 {
+                    return None;
+                }
+                kept_titles
+                    .iter()
+                    .position(|words| jaccard(words, &title_words) >= title_similarity)
+            });
+
+        match duplicate_of {
+            Some(k) => {
+                let source = article.article_source.source;
+                let original = &mut kept[k].1;
+                if source != original.article_source.source && !original.also_in.contains(&source) {
+                    original.also_in.push(source);
+                }
+                if let Some(key) = url_key {
+                    by_url.entry(key).or_insert(k);
+                }
+                removed += 1;
+            }
+            None => {
+                if let Some(key) = url_key {
+                    by_url.insert(key, kept.len());
+                }
+                kept_titles.push(title_words);
+                kept.push((idx, article));
+            }
+        }
+    }
+
+    if removed > 0 {
+        info!("Removed {} duplicate articles", removed);
+    }
+
+    kept.sort_by_key(|(idx, _)| *idx);
+    kept.into_iter().map(|(_, a)| a).collect()
+}
+
+fn title_words(title: &str) -> HashSet<String> {
+    title
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+        .collect()
+}
+
+fn jaccard(a: &HashSet<String>, b: &HashSet<String>) -> f64 {
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    let intersection = a.intersection(b).count();
+    let union = a.len() + b.len() - intersection;
+    intersection as f64 / union as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::feed::ArticleSource;
+
+    fn article(title: &str, link: &str, source: &str, position: i64) -> Article {
+        Article {
+            title: title.to_string(),
+            link: link.to_string(),
+            entry_id: link.to_string(),
+            article_source: ArticleSource {
+                position,
+                source: source.to_string(),
+                category: None,
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn keeps_highest_priority_copy_of_same_url() {
+        let articles = vec![
+            article("Story", "https://www.example.com/story/?utm_source=rss", "Aggregator", 5),
+            article("Story", "http://example.com/story#comments", "Blog", 1),
+            article("Other", "https://example.com/other", "Blog", 1),
+        ];
+
+        let deduped = dedup_articles(articles, 0.0);
+        assert_eq!(deduped.len(), 2);
+        assert_eq!(deduped[0].article_source.source, "Blog");
+        assert_eq!(deduped[0].link, "http://example.com/story#comments");
+        assert_eq!(deduped[0].also_in, vec!["Aggregator".to_string()]);
+        assert_eq!(deduped[1].title, "Other");
+    }
+
+    #[test]
+    fn title_similarity_is_opt_in() {
+        let articles = vec![
+            article("Rust 2.0 released today", "https://a.example/rust", "Top", 0),
+            article("Rust 2.0 released today!", "https://b.example/news/1", "World", 1),
+        ];
+
+        assert_eq!(dedup_articles(articles.clone(), 0.0).len(), 2);
+        let deduped = dedup_articles(articles, 0.8);
+        assert_eq!(deduped.len(), 1);
+        assert_eq!(deduped[0].also_in, vec!["World".to_string()]);
+    }
+}
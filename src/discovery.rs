@@ -0,0 +1,193 @@
+use dom_query::Document;
+use feed_rs::parser;
+use reqwest::header;
+use tracing::debug;
+use url::Url;
+use crate::models::FeedCandidate;
+use crate::util::http::HttpClient;
+
+/// Paths tried when a page does not advertise its feed with a `<link>` tag.
+const COMMON_FEED_PATHS: &[&str] = &["/feed", "/rss.xml"];
+/// Upper bound on advertised feeds that are fetched to verify them.
+const MAX_CANDIDATES: usize = 8;
+
+const FEED_LINK_TYPES: &[&str] = &[
+    "application/rss+xml",
+    "application/atom+xml",
+    "application/feed+json",
+    "application/json",
+];
+
+#[derive(Debug)]
+pub enum Discovery {
+    /// The URL is a feed, or the page it points to advertises exactly one.
+    Feed(FeedCandidate),
+    /// The page advertises several feeds; the best match comes first.
+    Candidates(Vec<FeedCandidate>),
+}
+
+/// Resolves a user supplied URL to a feed. Feed URLs are returned as is; HTML
+/// pages are searched for advertised feeds and, failing that, for feeds at
+/// [`COMMON_FEED_PATHS`]. Network errors are returned as `Err` so callers can
+/// decide whether to keep the URL anyway.
+pub async fn discover_feed(client: &HttpClient, url: &str) -> Result<Discovery, DiscoveryError> {
+    let page = fetch(client, url).await.map_err(DiscoveryError::Fetch)?;
+
+    if let Ok(feed) = parser::parse(page.body.as_bytes()) {
+        return Ok(Discovery::Feed(FeedCandidate {
+            url: url.to_string(),
+            title: feed.title.map(|t| t.content.trim().to_string()).filter(|t| !t.is_empty()),
+        }));
+    }
+    if !page.is_html {
+        return Err(DiscoveryError::NotFound(format!(
+            "{} is neither a feed nor an HTML page",
+            url
+        )));
+    }
+
+    let base = Url::parse(&page.final_url).map_err(|e| DiscoveryError::NotFound(e.to_string()))?;
+    let mut urls = advertised_feeds(&page.body, &base);
+    if urls.is_empty() {
+        urls = COMMON_FEED_PATHS
+            .iter()
+            .filter_map(|path| base.join(path).ok())
+            .map(|u| u.to_string())
+            .collect();
+    }
+
+    let mut candidates = Vec::new();
+    for candidate_url in urls.into_iter().take(MAX_CANDIDATES) {
+        match verify_feed(client, &candidate_url).await {
+            Some(candidate) => candidates.push(candidate),
+            None => debug!("Discovered feed {} could not be parsed", candidate_url),
+        }
+    }
+
+    match candidates.len() {
+        0 => Err(DiscoveryError::NotFound(format!("No feed found at {}", url))),
+        1 => Ok(Discovery::Feed(candidates.remove(0))),
+        _ => Ok(Discovery::Candidates(candidates)),
+    }
+}
+
+#[derive(Debug)]
+pub enum DiscoveryError {
+    /// The URL could not be downloaded.
+    Fetch(String),
+    /// The URL was downloaded but no feed could be found.
+    NotFound(String),
+}
+
+impl std::fmt::Display for DiscoveryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DiscoveryError::Fetch(msg) | DiscoveryError::NotFound(msg) => f.write_str(msg),
+        }
+    }
+}
+
+struct Page {
+    final_url: String,
+    is_html: bool,
+    body: String,
+}
+
+async fn fetch(client: &HttpClient, url: &str) -> Result<Page, String> {
+    let resp = client
+        .send(client.get(url))
+        .await
+        .map_err(|e| format!("Failed to fetch URL: {}", e))?;
+    if !resp.status().is_success() {
+        return Err(format!("Failed to fetch {}: HTTP {}", url, resp.status()));
+    }
+
+    let final_url = resp.url().to_string();
+    let content_type = resp
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+    let body = client
+        .text(resp)
+        .await
+        .map_err(|e| format!("Failed to read response body: {}", e))?;
+    let is_html = content_type.contains("html") || looks_like_html(&body);
+    Ok(Page { final_url, is_html, body })
+}
+
+async fn verify_feed(client: &HttpClient, url: &str) -> Option<FeedCandidate> {
+    let page = fetch(client, url).await.ok()?;
+    let feed = parser::parse(page.body.as_bytes()).ok()?;
+    Some(FeedCandidate {
+        url: url.to_string(),
+        title: feed.title.map(|t| t.content.trim().to_string()).filter(|t| !t.is_empty()),
+    })
+}
+
+fn looks_like_html(body: &str) -> bool {
+    let start: String = body.trim_start().chars().take(64).collect::<String>().to_ascii_lowercase();
+    start.starts_with("<!doctype html") || start.starts_with("<html")
+}
+
+/// Feed URLs advertised with `<link rel="alternate">`, resolved against the page
+/// URL (or its `<base href>`), in document order and without duplicates.
+fn advertised_feeds(html: &str, page_url: &Url) -> Vec<String> {
+    let doc = Document::from(html);
+    let base = doc
+        .select("base[href]")
+        .attr("href")
+        .and_then(|href| page_url.join(&href).ok())
+        .unwrap_or_else(|| page_url.clone());
+
+    let mut urls: Vec<String> = Vec::new();
+    for link in doc.select("link[rel][href]").nodes() {
+        let rel = link.attr("rel").map(|r| r.to_ascii_lowercase()).unwrap_or_default();
+        if !rel.split_whitespace().any(|r| r == "alternate") {
+            continue;
+        }
+        let link_type = link.attr("type").map(|t| t.trim().to_ascii_lowercase()).unwrap_or_default();
+        if !FEED_LINK_TYPES.contains(&link_type.as_str()) {
+            continue;
+        }
+        if let Some(href) = link.attr("href")
+            && let Ok(resolved) = base.join(href.trim())
+        {
+            let resolved = resolved.to_string();
+            if !urls.contains(&resolved) {
+                urls.push(resolved);
+            }
+        }
+    }
+    urls
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_advertised_feeds() {
+        let html = r#"<!DOCTYPE html><html><head>
+            <link rel="stylesheet" href="/style.css">
+            <link rel="alternate" type="application/rss+xml" title="Posts" href="/posts.xml">
+            <link rel="alternate" type="application/atom+xml" href="https://cdn.example.com/atom.xml">
+            <link rel="alternate" type="text/html" hreflang="de" href="/de/">
+            <link rel="Alternate" type="application/feed+json" href="feed.json">
+            <link rel="alternate" type="application/rss+xml" href="/posts.xml">
+            </head><body></body></html>"#;
+        let page = Url::parse("https://example.com/blog/").unwrap();
+
+        assert_eq!(
+            advertised_feeds(html, &page),
+            vec![
+                "https://example.com/posts.xml".to_string(),
+                "https://cdn.example.com/atom.xml".to_string(),
+                "https://example.com/blog/feed.json".to_string(),
+            ]
+        );
+        assert!(looks_like_html(html));
+        assert!(!looks_like_html("<?xml version=\"1.0\"?><rss></rss>"));
+    }
+}
@@ -154,11 +154,13 @@ pub async fn send_epub(config: &EmailConfig, epub_path: &Path) -> Result<()> {
     Ok(())
 }
 
+/// Sends the EPUB if auto-send is on. Returns whether the EPUB reached its
+/// reader: it was sent, or auto-send is off and the saved file is the delivery.
 pub async fn check_and_send_email(
     db: Arc<Mutex<Connection>>,
     filename: &String,
     override_to_email: Option<&str>,
-) -> Result<()> {
+) -> Result<bool> {
     let send_email = {
         let conn = db.lock().map_err(|_| anyhow::anyhow!("DB lock failed"))?;
         match db::get_email_config(&conn)? {
@@ -183,12 +185,12 @@ pub async fn check_and_send_email(
             let epub_path = std::path::Path::new(crate::util::EPUB_OUTPUT_DIR).join(&filename);
             if let Err(e) = send_epub(&config, &epub_path).await {
                 error!("Failed to auto-send email: {}", e);
-            } else {
-                info!("Auto-send email sent successfully.");
+                return Ok(false);
             }
+            info!("Auto-send email sent successfully.");
         }
     }
-    Ok(())
+    Ok(true)
 }
 
 #[cfg(test)]
@@ -0,0 +1,201 @@
+use std::borrow::Cow;
+use dom_query::Document;
+use feed_rs::model::Entry;
+use regex::Regex;
+use tracing::warn;
+use crate::models::{FilterAction, FilterField, FilterMatchType, FilterRule};
+
+enum Matcher {
+    Keyword(String),
+    Regex(Regex),
+}
+
+impl Matcher {
+    fn is_match(&self, text: &str) -> bool {
+        match self {
+            Matcher::Keyword(keyword) => text.to_lowercase().contains(keyword),
+            Matcher::Regex(re) => re.is_match(text),
+        }
+    }
+}
+
+struct CompiledRule {
+    action: FilterAction,
+    field: FilterField,
+    matcher: Matcher,
+}
+
+/// Compiled filter rules for one feed. An entry is kept when it matches no exclude
+/// rule and, if the feed has include rules, at least one of them.
+pub struct EntryFilter {
+    rules: Vec<CompiledRule>,
+    has_include: bool,
+}
+
+impl EntryFilter {
+    /// Rules with an invalid regex are skipped with a warning.
+    pub fn new(rules: &[FilterRule]) -> Self {
+        let rules: Vec<CompiledRule> = rules
+            .iter()
+            .filter_map(|rule| {
+                let matcher = match compile_matcher(rule) {
+                    Ok(m) => m,
+                    Err(e) => {
+                        warn!("Skipping filter rule {:?}: {}", rule.id, e);
+                        return None;
+                    }
+                };
+                Some(CompiledRule { action: rule.action, field: rule.field, matcher })
+            })
+            .collect();
+        let has_include = rules.iter().any(|r| r.action == FilterAction::Include);
+        Self { rules, has_include }
+    }
+
+    pub fn accepts(&self, entry: &Entry) -> bool {
+        if self.rules.is_empty() {
+            return true;
+        }
+
+        let mut included = !self.has_include;
+        for rule in &self.rules {
+            let matched = field_values(entry, rule.field).iter().any(|v| rule.matcher.is_match(v));
+            match rule.action {
+                FilterAction::Exclude if matched => return false,
+                FilterAction::Include if matched => included = true,
+                _ => {}
+            }
+        }
+        included
+    }
+}
+
+/// Checks that a rule's pattern is usable before it is stored.
+pub fn validate_rule(rule: &FilterRule) -> Result<(), String> {
+    if rule.pattern.trim().is_empty() {
+        return Err("pattern must not be empty".to_string());
+    }
+    compile_matcher(rule).map(|_| ())
+}
+
+fn compile_matcher(rule: &FilterRule) -> Result<Matcher, String> {
+    match rule.match_type {
+        FilterMatchType::Keyword => Ok(Matcher::Keyword(rule.pattern.trim().to_lowercase())),
+        FilterMatchType::Regex => Regex::new(&rule.pattern)
+            .map(Matcher::Regex)
+            .map_err(|e| format!("invalid regex: {}", e)),
+    }
+}
+
+fn field_values(entry: &Entry, field: FilterField) -> Vec<Cow<'_, str>> {
+    let mut values: Vec<Cow<str>> = Vec::new();
+    if matches!(field, FilterField::Title | FilterField::Any)
+        && let Some(title) = &entry.title
+    {
+        values.push(title.content.as_str().into());
+    }
+    if matches!(field, FilterField::Author | FilterField::Any) {
+        values.extend(entry.authors.iter().map(|a| a.name.as_str().into()));
+    }
+    if matches!(field, FilterField::Tags | FilterField::Any) {
+        for category in &entry.categories {
+            values.push(category.term.as_str().into());
+            if let Some(label) = &category.label {
+                values.push(label.as_str().into());
+            }
+        }
+    }
+    if matches!(field, FilterField::Summary | FilterField::Any) {
+        let html = entry
+            .summary
+            .as_ref()
+            .map(|s| s.content.as_str())
+            .or_else(|| entry.content.as_ref().and_then(|c| c.body.as_deref()));
+        if let Some(html) = html {
+            values.push(html_text(html));
+        }
+    }
+    values
+}
+
+/// The text of an HTML summary, so rules don't match tag names, attributes or
+/// entities.
+fn html_text(html: &str) -> Cow<'_, str> {
+    if !html.contains(['<', '&']) {
+        return html.into();
+    }
+    Document::from(html).select("body").text().to_string().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(action: FilterAction, match_type: FilterMatchType, field: FilterField, pattern: &str) -> FilterRule {
+        FilterRule {
+            id: None,
+            feed_id: Some(1),
+            category_id: None,
+            action,
+            match_type,
+            field,
+            pattern: pattern.to_string(),
+        }
+    }
+
+    fn entry(title: &str, author: &str, tag: &str) -> Entry {
+        let xml = format!(
+            "<rss version=\"2.0\" xmlns:dc=\"http://purl.org/dc/elements/1.1/\"><channel><title>t</title>\
+             <item><title>{}</title><dc:creator>{}</dc:creator><category>{}</category></item>\
+             </channel></rss>",
+            title, author, tag
+        );
+        feed_rs::parser::parse(xml.as_bytes()).unwrap().entries.remove(0)
+    }
+
+    #[test]
+    fn exclude_rules_drop_matching_entries() {
+        let filter = EntryFilter::new(&[rule(
+            FilterAction::Exclude,
+            FilterMatchType::Keyword,
+            FilterField::Title,
+            "Sponsored",
+        )]);
+        assert!(!filter.accepts(&entry("SPONSORED: buy now", "Ads", "promo")));
+        assert!(filter.accepts(&entry("Release notes", "Jane", "rust")));
+    }
+
+    #[test]
+    fn include_rules_keep_only_matching_entries() {
+        let filter = EntryFilter::new(&[
+            rule(FilterAction::Include, FilterMatchType::Regex, FilterField::Tags, "^(rust|wasm)$"),
+            rule(FilterAction::Include, FilterMatchType::Keyword, FilterField::Author, "jane"),
+            rule(FilterAction::Exclude, FilterMatchType::Keyword, FilterField::Any, "rumor"),
+        ]);
+        assert!(filter.accepts(&entry("Release notes", "Someone", "rust")));
+        assert!(filter.accepts(&entry("Weekly update", "Jane Doe", "misc")));
+        assert!(!filter.accepts(&entry("Weekly update", "Someone", "misc")));
+        assert!(!filter.accepts(&entry("Rumor: rust 2.0", "Jane", "rust")));
+    }
+
+    #[test]
+    fn summary_rules_match_text_not_markup() {
+        let xml = "<rss version=\"2.0\"><channel><title>t</title><item><title>Post</title>\
+            <description>&lt;p class=\"sponsored\"&gt;Tom &amp;amp; Jerry&lt;/p&gt;</description>\
+            </item></channel></rss>";
+        let entry = feed_rs::parser::parse(xml.as_bytes()).unwrap().entries.remove(0);
+        let excludes = |match_type, pattern| {
+            !EntryFilter::new(&[rule(FilterAction::Exclude, match_type, FilterField::Summary, pattern)]).accepts(&entry)
+        };
+        assert!(!excludes(FilterMatchType::Keyword, "sponsored"));
+        assert!(!excludes(FilterMatchType::Regex, "<p"));
+        assert!(excludes(FilterMatchType::Keyword, "tom & jerry"));
+        assert!(excludes(FilterMatchType::Regex, "^Tom & Jerry$"));
+    }
+
+    #[test]
+    fn invalid_regex_is_rejected() {
+        assert!(validate_rule(&rule(FilterAction::Include, FilterMatchType::Regex, FilterField::Title, "(")).is_err());
+        assert!(validate_rule(&rule(FilterAction::Include, FilterMatchType::Keyword, FilterField::Title, " ")).is_err());
+    }
+}
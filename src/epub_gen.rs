@@ -1,6 +1,6 @@
 use std::collections::{BTreeMap, BTreeSet, HashMap};
 use crate::models::epub_message::EpubPart;
-use crate::models::{CoverTextColor, CoverTextPosition, CoverTextSize};
+use crate::models::{CoverTextColor, CoverTextPosition, CoverTextSize, HtmlProfile};
 use crate::feed::{Article, ArticleSource};
 use crate::image::process_images;
 use crate::templates::{XhtmlWrapper, MasterToc, TocEntry, SourceToc, ArticleEntry, ArticleTemplate, CategoryGroup, CoverTemplate};
@@ -19,6 +19,7 @@ use tokio::task::JoinSet;
 use tracing::info;
 
 const EPUB_LANGUAGE_ENV: &str = "RSSPUB_EPUB_LANGUAGE";
+const EPUB_STYLESHEET: &str = include_str!("../templates/stylesheet.css");
 
 fn resolve_epub_language(value: Option<String>) -> String {
     value
@@ -52,12 +53,32 @@ impl Default for CoverTextConfig {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct EpubOptions {
+    pub image_timeout_seconds: i32,
+    pub cover_text: CoverTextConfig,
+    /// Turn external links in article bodies into numbered endnotes.
+    pub link_endnotes: bool,
+    pub html_profile: HtmlProfile,
+}
+
+impl Default for EpubOptions {
+    fn default() -> Self {
+        Self {
+            image_timeout_seconds: 45,
+            cover_text: CoverTextConfig::default(),
+            link_endnotes: false,
+            html_profile: HtmlProfile::default(),
+        }
+    }
+}
+
 pub async fn generate_epub_data<W: Write + Seek + Send + 'static>(
     articles: &[Article],
     output: W,
-    image_timeout_seconds: i32,
-    cover_text: CoverTextConfig,
+    options: EpubOptions,
 ) -> Result<()> {
+    let EpubOptions { image_timeout_seconds, cover_text, link_endnotes, html_profile } = options;
     use crate::models::epub_message::{CompletionMessage, EpubPart};
     use crate::util;
     use std::collections::HashMap;
@@ -129,6 +150,9 @@ pub async fn generate_epub_data<W: Write + Seek + Send + 'static>(
                 format!("RSS Digest - {}", Utc::now().format("%Y-%m-%d")),
             )
             .map_err(|e| anyhow::anyhow!("{}", e))?;
+        builder
+            .stylesheet(EPUB_STYLESHEET.as_bytes())
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
 
         let cover_path = util::COVER_LOCATION;
         if std::path::Path::new(cover_path).exists() {
@@ -321,17 +345,27 @@ pub async fn generate_epub_data<W: Write + Seek + Send + 'static>(
         let tx_m = tx_m.clone();
         let counter_ref = Arc::clone(&counter);
         join_set.spawn(async move {
-            let cleaned_content = util::clean_html(&article.content);
+            let cleaned_content = util::clean_html(&util::normalize_images(&article.content), html_profile);
             let (processed_content,total_images_for_seq) = process_images(&cleaned_content,&tx_m,&seq_id, image_timeout_seconds as u64).await;
             counter_ref.fetch_add(total_images_for_seq, Ordering::Relaxed);
+            let (processed_content, link_notes) = if link_endnotes {
+                util::link_notes::links_to_noterefs(&processed_content)
+            } else {
+                (processed_content, Vec::new())
+            };
             let fixed_content = util::fix_xhtml(&processed_content);
+            let original_link = util::urls::clean_url(&article.link);
 
             let article_template = ArticleTemplate {
                 title: &article.title,
                 source: &article.article_source.source,
+                author: article.author.as_deref(),
+                also_in: &article.also_in,
                 pub_date: article.pub_date.format("%Y-%m-%d %H:%M").to_string(),
                 content: &fixed_content,
-                original_link: &article.link,
+                original_link: &original_link,
+                fetched_from: article.fetched_from.as_deref(),
+                link_notes: &link_notes,
                 back_link,
                 prev_link,
                 next_link,
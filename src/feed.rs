@@ -1,25 +1,72 @@
+use std::collections::HashMap;
 use std::hash::{Hash};
 use chrono::{DateTime, Utc};
-use feed_rs::model::Feed;
+use feed_rs::model::{Entry, Feed};
 use feed_rs::parser;
-use reqwest::Client;
-use std::sync::Arc;
-use std::time::Duration;
+use base64::Engine;
+use reqwest::header::{self, HeaderMap, HeaderName, HeaderValue};
+use reqwest::StatusCode;
+use rusqlite::Connection;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
 use tracing::{error, info, warn};
-use crate::models::ContentProcessor;
-use crate::util::content_extractors;
+use crate::db::{feed_cache_db, feed_health_db, feed_request_options_db, filter_rule_db};
+use crate::entry_filter::EntryFilter;
+use crate::models::{
+    ArticleOrder, ContentProcessor, DeliveredArticles, FeedAuth, FeedHttpCache, FeedRequestOptions, FeedSourceType,
+    FilterRule,
+};
+use crate::util::{content_extractors, html_feed, secrets, urls};
+use crate::util::article_cache::ArticleCache;
+use crate::util::http::HttpClient;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct Article {
     pub title: String,
     pub link: String,
     pub content: String,
     pub pub_date: DateTime<Utc>,
     pub article_source: ArticleSource,
+    /// Feed entry id (GUID); empty for articles that did not come from a feed entry.
+    pub entry_id: String,
+    /// Other sources that carried the same story.
+    pub also_in: Vec<String>,
+    /// URL the content was read from when a fallback replaced `link`.
+    pub fetched_from: Option<String>,
+    pub author: Option<String>,
 }
 
-#[derive(Debug, Clone, PartialEq, Hash, Eq,Ord,PartialOrd)]
+/// Decides which feed entries `filter_items` turns into articles.
+pub enum EntrySelection {
+    /// Entries published or updated at or after the given time; undated entries are skipped.
+    Since(DateTime<Utc>),
+    /// Entries not yet delivered, if undated or published at or after `since`.
+    Undelivered { delivered: DeliveredArticles, since: DateTime<Utc> },
+}
+
+impl EntrySelection {
+    /// The date an entry is filed under, or `None` when the selection skips it.
+    /// Undated entries picked by `Undelivered` are dated now.
+    fn select(&self, entry: &Entry, link: &str) -> Option<DateTime<Utc>> {
+        let entry_date = entry.published.or(entry.updated);
+        match self {
+            EntrySelection::Since(since) => entry_date.filter(|date| date >= since),
+            EntrySelection::Undelivered { delivered, since } => {
+                if delivered.contains(&entry.id, link) {
+                    return None;
+                }
+                match entry_date {
+                    Some(date) => (date >= *since).then_some(date),
+                    None => Some(Utc::now()),
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Hash, Eq,Ord,PartialOrd)]
 pub struct ArticleSource{
     pub position: i64,
     pub source: String,
@@ -33,83 +80,354 @@ pub struct FeedWrapper {
     pub processor: Option<ContentProcessor>,
     pub name: Option<String>,
     pub category: Option<String>,
+    /// The feed's own filter rules plus those of its category.
+    pub filter_rules: Vec<FilterRule>,
+    pub max_articles: usize,
+    pub article_order: ArticleOrder,
+    /// Extra headers from the feed's request options.
+    pub request_headers: HeaderMap,
+    /// Host of the feed URL; request headers are only sent to this host.
+    pub feed_host: Option<String>,
 }
 
+/// Upper bound on feeds being downloaded at the same time.
+const FEED_FETCH_CONCURRENCY: usize = 16;
+/// Upper bound on simultaneous requests to a single host, so many feeds from
+/// one publisher are fetched politely.
+const FEED_FETCH_PER_HOST: usize = 2;
+
 pub async fn fetch_feeds(
-    db_feeds: &Vec<crate::models::Feed>,
+    db_feeds: &[crate::models::Feed],
+    client: &HttpClient,
+    db: &Arc<Mutex<Connection>>,
 ) -> (Vec<FeedWrapper>, Vec<(String, String)>) {
-    let client = Client::builder()
-        .user_agent("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")
-        .timeout(Duration::from_secs( 45))
-        .cookie_store(true)
-        .build()
-        .unwrap_or_else(|_| Client::new());
+    let (auto_pause_after_failures, filter_rules, mut request_options) = match db.lock() {
+        Ok(conn) => (
+            crate::db::get_general_config(&conn)
+                .map(|c| c.auto_pause_after_failures)
+                .unwrap_or(0),
+            filter_rule_db::get_filter_rules(&conn).unwrap_or_else(|e| {
+                warn!("Failed to load filter rules: {}", e);
+                Vec::new()
+            }),
+            feed_request_options_db::get_sealed_request_options(&conn).unwrap_or_else(|e| {
+                warn!("Failed to load feed request options: {}", e);
+                HashMap::new()
+            }),
+        ),
+        Err(_) => (0, Vec::new(), HashMap::new()),
+    };
+
+    let global_limit = Arc::new(Semaphore::new(FEED_FETCH_CONCURRENCY));
+    let mut host_limits: HashMap<String, Arc<Semaphore>> = HashMap::new();
+    let mut feed_headers: HashMap<usize, HeaderMap> = HashMap::new();
+    let mut join_set = JoinSet::new();
+
+    for (index, db_feed) in db_feeds.iter().cloned().enumerate() {
+        if db_feed.paused {
+            info!("Skipping paused feed: {}", db_feed.url);
+            continue;
+        }
+        let host = content_extractors::extract_domain(&db_feed.url).unwrap_or_default();
+        let host_limit = host_limits
+            .entry(host)
+            .or_insert_with(|| Arc::new(Semaphore::new(FEED_FETCH_PER_HOST)))
+            .clone();
+        let global_limit = global_limit.clone();
+        let client = client.clone();
+        let db = db.clone();
+        let headers = match db_feed.id.and_then(|id| request_options.remove(&id)) {
+            Some(sealed) => unseal_request_headers(&sealed),
+            None => Ok(HeaderMap::new()),
+        };
+        if let Ok(headers) = &headers
+            && !headers.is_empty()
+        {
+            feed_headers.insert(index, headers.clone());
+        }
+
+        join_set.spawn(async move {
+            // Wait for the host slot first so a busy host does not hold global slots.
+            let _host_permit = host_limit.acquire_owned().await.ok();
+            let _permit = global_limit.acquire_owned().await.ok();
+            let started = Instant::now();
+            let result = match &headers {
+                Ok(headers) => fetch_feed(&client, &db_feed, headers, &db).await,
+                Err(e) => Err(FeedFetchError::new(e.clone(), None)),
+            };
+            let elapsed_ms = started.elapsed().as_secs_f64() * 1000.0;
+            (index, db_feed, result, elapsed_ms)
+        });
+    }
+
+    let mut results = Vec::with_capacity(db_feeds.len());
+    while let Some(res) = join_set.join_next().await {
+        match res {
+            Ok(result) => results.push(result),
+            Err(e) => error!("Feed fetch task join error: {}", e),
+        }
+    }
+    results.sort_by_key(|(index, _, _, _)| *index);
+    record_feed_health(db, &results, auto_pause_after_failures);
 
     let mut feeds = Vec::new();
     let mut errors = Vec::new();
-    let feed_info: Vec<(String, usize, Option<ContentProcessor>,i64, Option<String>, Option<String>)> = db_feeds
-        .into_iter()
-        .map(|f| (f.url.clone(), f.concurrency_limit, Some(f.feed_processor.clone()),f.position, f.name.clone(), f.category.clone()))
-        .collect();
-    for (string_url, limit, processor,pos, name, category) in feed_info {
-        let url: &str = &string_url;
-        match client.get(url).send().await {
-            Ok(resp) => {
-                if !resp.status().is_success() {
-                    let msg = format!("Failed to fetch feed: HTTP {}", resp.status());
-                    warn!("{} - {}", msg, url);
-                    errors.push((format!("{}", url), msg));
-                    continue;
-                }
-
-                match resp.bytes().await {
-                    Ok(content) => match parser::parse(&content[..]) {
-                        Ok(feed) => {
-                            info!("Successfully fetched and parsed feed: {}", url);
-                            feeds.push(FeedWrapper {
-                                feed,
-                                position: pos,
-                                limit,
-                                processor,
-                                name,
-                                category,
-                            });
-                        }
-                        Err(e) => {
-                            let msg = format!("Failed to parse RSS feed: {}", e);
-                            warn!("{} - {}", msg, url);
-                            errors.push((format!("{}", url), msg));
-                        }
-                    },
-                    Err(e) => {
-                        let msg = format!("Failed to read response body: {}", e);
-                        warn!("{} - {}", msg, url);
-                        errors.push((format!("{}", url), msg));
-                    }
+    let mut cache_hits = 0;
+    let mut cache_misses = 0;
+    for (index, db_feed, result, _) in results {
+        let url = db_feed.url;
+        match result {
+            Ok(FetchedFeed { feed, cache_hit, .. }) => {
+                if cache_hit {
+                    cache_hits += 1;
+                    info!("Feed not modified, reusing cached parse: {}", url);
+                } else {
+                    cache_misses += 1;
+                    info!("Successfully fetched and parsed feed: {}", url);
                 }
+                feeds.push(FeedWrapper {
+                    feed,
+                    filter_rules: filter_rule_db::rules_for_feed(&filter_rules, db_feed.id, db_feed.category_id),
+                    max_articles: db_feed.max_articles,
+                    article_order: db_feed.article_order,
+                    request_headers: feed_headers.remove(&index).unwrap_or_default(),
+                    feed_host: content_extractors::extract_domain(&url),
+                    position: db_feed.position,
+                    limit: db_feed.concurrency_limit,
+                    processor: Some(db_feed.feed_processor),
+                    name: db_feed.name,
+                    category: db_feed.category,
+                });
             }
             Err(e) => {
-                let msg = format!("Failed to fetch URL: {}", e);
-                warn!("{} - {}", msg, url);
-                errors.push((format!("{}", url), msg));
+                warn!("{} - {}", e.message, url);
+                errors.push((url, e.message));
             }
         }
     }
+    info!("Feed cache: {} hits, {} misses", cache_hits, cache_misses);
 
     (feeds, errors)
 }
 
+struct FetchedFeed {
+    feed: Feed,
+    cache_hit: bool,
+    http_status: u16,
+}
+
+struct FeedFetchError {
+    message: String,
+    http_status: Option<u16>,
+}
+
+impl FeedFetchError {
+    fn new(message: String, http_status: Option<u16>) -> Self {
+        Self { message, http_status }
+    }
+}
+
+fn record_feed_health(
+    db: &Arc<Mutex<Connection>>,
+    results: &[(usize, crate::models::Feed, Result<FetchedFeed, FeedFetchError>, f64)],
+    auto_pause_after_failures: i32,
+) {
+    let Ok(conn) = db.lock() else {
+        error!("Failed to lock DB to record feed health");
+        return;
+    };
+    for (_, db_feed, result, elapsed_ms) in results {
+        let Some(feed_id) = db_feed.id else { continue };
+        let recorded = match result {
+            Ok(fetched) => feed_health_db::record_fetch_success(
+                &conn,
+                feed_id,
+                Some(fetched.http_status),
+                *elapsed_ms,
+            ),
+            Err(e) => feed_health_db::record_fetch_failure(
+                &conn,
+                feed_id,
+                e.http_status,
+                &e.message,
+                *elapsed_ms,
+                auto_pause_after_failures,
+            )
+            .map(|paused| {
+                if paused {
+                    warn!(
+                        "Paused feed {} after {} consecutive failures",
+                        db_feed.url, auto_pause_after_failures
+                    );
+                }
+            }),
+        };
+        if let Err(e) = recorded {
+            warn!("Failed to record health for feed {}: {}", db_feed.url, e);
+        }
+    }
+}
+
+/// Fetches a single feed, sending the stored validators so an unchanged feed
+/// answers with 304 and the cached parse is reused.
+async fn fetch_feed(
+    client: &HttpClient,
+    db_feed: &crate::models::Feed,
+    request_headers: &HeaderMap,
+    db: &Arc<Mutex<Connection>>,
+) -> Result<FetchedFeed, FeedFetchError> {
+    let url = db_feed.url.as_str();
+    let cached = match db.lock() {
+        Ok(conn) => feed_cache_db::get_feed_cache(&conn, url).unwrap_or_else(|e| {
+            warn!("Failed to read feed cache for {}: {}", url, e);
+            None
+        }),
+        Err(_) => None,
+    };
+
+    let mut request = client.get(url).headers(request_headers.clone());
+    if let Some(cache) = &cached {
+        if let Some(etag) = &cache.etag {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &cache.last_modified {
+            request = request.header(header::IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+
+    let resp = client
+        .send(request)
+        .await
+        .map_err(|e| FeedFetchError::new(format!("Failed to fetch URL: {}", e), None))?;
+    let http_status = resp.status().as_u16();
+
+    if resp.status() == StatusCode::NOT_MODIFIED
+        && let Some(cache) = cached
+    {
+        return match serde_json::from_str::<Feed>(&cache.parsed_feed) {
+            Ok(feed) => Ok(FetchedFeed { feed, cache_hit: true, http_status }),
+            Err(e) => {
+                if let Ok(conn) = db.lock() {
+                    let _ = feed_cache_db::delete_feed_cache(&conn, url);
+                }
+                Err(FeedFetchError::new(
+                    format!("Feed not modified but cached copy is unreadable: {}", e),
+                    Some(http_status),
+                ))
+            }
+        };
+    }
+
+    if !resp.status().is_success() {
+        return Err(FeedFetchError::new(
+            format!("Failed to fetch feed: HTTP {}", resp.status()),
+            Some(http_status),
+        ));
+    }
+
+    let etag = header_string(resp.headers(), header::ETAG);
+    let last_modified = header_string(resp.headers(), header::LAST_MODIFIED);
+    let final_url = resp.url().to_string();
+
+    let content = client
+        .bytes(resp)
+        .await
+        .map_err(|e| FeedFetchError::new(format!("Failed to read response body: {}", e), Some(http_status)))?;
+    let feed = parse_source(db_feed, &content[..], &final_url)
+        .map_err(|e| FeedFetchError::new(e, Some(http_status)))?;
+
+    if etag.is_some() || last_modified.is_some() {
+        match serde_json::to_string(&feed) {
+            Ok(parsed_feed) => {
+                let cache = FeedHttpCache {
+                    url: url.to_string(),
+                    etag,
+                    last_modified,
+                    parsed_feed,
+                };
+                if let Ok(conn) = db.lock()
+                    && let Err(e) = feed_cache_db::save_feed_cache(&conn, &cache)
+                {
+                    warn!("Failed to store feed cache for {}: {}", url, e);
+                }
+            }
+            Err(e) => warn!("Failed to serialize feed for cache {}: {}", url, e),
+        }
+    }
+
+    Ok(FetchedFeed { feed, cache_hit: false, http_status })
+}
+
+/// Builds the headers described by a feed's request options.
+pub fn request_headers(options: &FeedRequestOptions) -> Result<HeaderMap, String> {
+    let mut headers = HeaderMap::new();
+    for h in &options.headers {
+        let name = HeaderName::from_bytes(h.name.trim().as_bytes())
+            .map_err(|_| format!("Invalid header name: {}", h.name))?;
+        headers.insert(name, sensitive_value(&h.value)?);
+    }
+    match &options.auth {
+        FeedAuth::None => {}
+        FeedAuth::Basic { username, password } => {
+            let credentials = base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", username, password));
+            headers.insert(header::AUTHORIZATION, sensitive_value(&format!("Basic {}", credentials))?);
+        }
+        FeedAuth::Bearer { token } => {
+            headers.insert(header::AUTHORIZATION, sensitive_value(&format!("Bearer {}", token.trim()))?);
+        }
+    }
+    if let Some(cookie) = options.cookie.as_deref().filter(|c| !c.trim().is_empty()) {
+        headers.insert(header::COOKIE, sensitive_value(cookie)?);
+    }
+    Ok(headers)
+}
+
+fn sensitive_value(value: &str) -> Result<HeaderValue, String> {
+    let mut value = HeaderValue::from_str(value.trim()).map_err(|_| "Invalid header value".to_string())?;
+    value.set_sensitive(true);
+    Ok(value)
+}
+
+fn unseal_request_headers(sealed: &str) -> Result<HeaderMap, String> {
+    let json = secrets::open(sealed).map_err(|e| format!("Failed to read feed request options: {}", e))?;
+    let options: FeedRequestOptions =
+        serde_json::from_str(&json).map_err(|e| format!("Failed to read feed request options: {}", e))?;
+    request_headers(&options)
+}
+
+fn parse_source(db_feed: &crate::models::Feed, content: &[u8], final_url: &str) -> Result<Feed, String> {
+    match db_feed.source_type {
+        FeedSourceType::Feed => {
+            parser::parse(content).map_err(|e| format!("Failed to parse RSS feed: {}", e))
+        }
+        FeedSourceType::Html => {
+            let config = db_feed
+                .scrape_config
+                .as_deref()
+                .ok_or_else(|| "HTML source has no scrape config".to_string())?;
+            let config = html_feed::parse_scrape_config(config).map_err(|e| e.to_string())?;
+            let html = String::from_utf8_lossy(content);
+            html_feed::scrape_listing(&html, final_url, &config)
+                .map_err(|e| format!("Failed to scrape listing page: {}", e))
+        }
+    }
+}
+
+fn header_string(headers: &HeaderMap, name: header::HeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string())
+}
+
 pub async fn filter_items(
     feeds: Vec<FeedWrapper>,
     errors: Vec<(String, String)>,
-    since: DateTime<Utc>,
+    selection: EntrySelection,
+    client: &HttpClient,
+    cache: Option<&ArticleCache>,
 ) -> Vec<Article> {
     let mut articles = Vec::new();
-    let client = Client::builder()
-        .user_agent("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")
-        .cookie_store(true)
-        .build()
-        .unwrap_or_else(|_| Client::new());
     let mut join_set = tokio::task::JoinSet::new();
 
     for (url, error_msg) in errors {
@@ -124,9 +442,12 @@ pub async fn filter_items(
             content: format!("<h1>Error loading feed</h1><p><strong>URL:</strong> {}</p><p><strong>Error:</strong> {}</p>", url, error_msg),
             pub_date: Utc::now(),
             article_source,
+            ..Default::default()
         });
     }
 
+    let mut filtered = 0;
+    let mut capped = 0;
     for feed_wrapper in feeds {
         let feed = feed_wrapper.feed;
         let limit = feed_wrapper.limit;
@@ -140,76 +461,137 @@ pub async fn filter_items(
             .filter(|n| !n.is_empty())
             .or_else(|| feed.title.map(|t| t.content))
             .unwrap_or_else(|| "Unknown Source".to_string());
+        let entry_filter = EntryFilter::new(&feed_wrapper.filter_rules);
+        let mut selected = Vec::new();
         for entry in feed.entries {
-            if let Some(pub_date) = entry.published.or(entry.updated) {
-                if pub_date >= since {
-                    let title = entry
-                        .title
-                        .as_ref()
-                        .map(|t| t.content.clone())
-                        .unwrap_or("No Title".to_string());
-
-                    let link = entry
-                        .links
-                        .iter()
-                        .find(|l| l.rel.as_deref() == Some("alternate") || l.rel.is_none())
-                        .map(|l| l.href.clone())
-                        .unwrap_or_default();
-
-                    let client = client.clone();
-                    let source_title = source_title.clone();
-                    let entry = entry.clone();
-                    let semaphore = semaphore.clone();
-                    let processor = processor.clone();
-                    let position = feed_wrapper.position;
-                    let category = feed_wrapper.category.clone();
-
-                    join_set.spawn(async move {
-                        let _permit = if let Some(sem) = semaphore {
-                            Some(sem.acquire_owned().await.unwrap())
-                        } else {
-                            None
-                        };
-                        info!("Processing article: {}", title);
-
-
-                        let content = if !link.is_empty() {
-                            match content_extractors::fetch_full_content_with_processor(&client, &link, processor.as_ref()).await {
-                                //should use extracted title ?
-                                Ok((_title, c)) => c,
-                                Err(e) => {
-                                    error!("Error fetching full content for '{}': {}", link, e);
-                                    let error_html = format!("<p style=\"color:red\"><strong>Error fetching full content:</strong> {}</p><hr/>", e);
-                                    let fallback = entry.content.map(|c| c.body.unwrap_or_default())
-                                        .or(entry.summary.map(|s| s.content))
-                                        .unwrap_or_default();
-                                    format!("{}{}", error_html, fallback)
-                                }
+            let link = urls::clean_url(&entry_link(&entry));
+            let Some(pub_date) = selection.select(&entry, &link) else {
+                continue;
+            };
+            if !entry_filter.accepts(&entry) {
+                filtered += 1;
+                continue;
+            }
+            selected.push((entry, link, pub_date));
+        }
+
+        let available = selected.len();
+        apply_article_limit(&mut selected, feed_wrapper.max_articles, feed_wrapper.article_order);
+        capped += available - selected.len();
+
+        for (entry, link, pub_date) in selected {
+            // Pages are fetched as linked, less tracking parameters; the cleaned
+            // link is only what the article shows and is deduplicated by.
+            let fetch_url = urls::strip_tracking_params(&entry_link(&entry));
+            let title = entry
+                .title
+                .as_ref()
+                .map(|t| t.content.clone())
+                .unwrap_or("No Title".to_string());
+
+            let client = client.clone();
+            let cache = cache.cloned();
+            let source_title = source_title.clone();
+            let entry = entry.clone();
+            let semaphore = semaphore.clone();
+            let processor = processor.clone();
+            let position = feed_wrapper.position;
+            let category = feed_wrapper.category.clone();
+            // Credentials stay with the feed's own host, not sites it links to.
+            let headers = if feed_wrapper.feed_host.is_some()
+                && content_extractors::extract_domain(&fetch_url) == feed_wrapper.feed_host
+            {
+                feed_wrapper.request_headers.clone()
+            } else {
+                HeaderMap::new()
+            };
+
+            join_set.spawn(async move {
+                let _permit = if let Some(sem) = semaphore {
+                    Some(sem.acquire_owned().await.unwrap())
+                } else {
+                    None
+                };
+                info!("Processing article: {}", title);
+
+
+                let feed_body = entry.content.map(|c| c.body.unwrap_or_default())
+                    .or(entry.summary.map(|s| s.content))
+                    .unwrap_or_default();
+                // Feed content is written relative to the entry's page.
+                let feed_body = match url::Url::parse(&fetch_url) {
+                    Ok(base) => urls::absolutize_urls(&feed_body, &base),
+                    Err(_) => feed_body,
+                };
+                // A domain override takes precedence over the feed's processor.
+                let domain_override = if fetch_url.is_empty() {
+                    None
+                } else {
+                    content_extractors::get_domain_override(&fetch_url)
+                };
+                let processor = domain_override.as_deref().or(processor.as_ref());
+
+                let mut title = title;
+                let mut author = None;
+                let mut canonical_url = None;
+                let mut pub_date = pub_date;
+                let (content, fetched_from) = if fetch_url.is_empty() || content_extractors::prefers_feed_content(processor, &feed_body) {
+                    (feed_body, None)
+                } else {
+                    match content_extractors::fetch_full_content_with_processor(&client, &fetch_url, processor, &headers, Some(&feed_body), cache.as_ref()).await {
+                        Ok(fetched) => {
+                            // Feed titles win unless the override picks the title out of the page.
+                            if content_extractors::extracts_title(processor) && !fetched.title.trim().is_empty() {
+                                title = fetched.title.trim().to_string();
                             }
-                        } else {
-                            entry.content.map(|c| c.body.unwrap_or_default())
-                                .or(entry.summary.map(|s| s.content))
-                                .unwrap_or_default()
-                        };
-                        let article_source =ArticleSource {
-                            source:source_title,
-                            position,
-                            category,
-                        };
-
-                        Article {
-                            title,
-                            link,
-                            content,
-                            pub_date,
-                            article_source,
+                            author = fetched.metadata.author;
+                            // Undated entries were given the current time; the page may know better.
+                            if entry.published.or(entry.updated).is_none()
+                                && let Some(published) = fetched.metadata.published
+                            {
+                                pub_date = published;
+                            }
+                            // A fallback's canonical link names the fallback, not the article.
+                            canonical_url = fetched.metadata.canonical_url.filter(|_| fetched.source_url == fetch_url);
+                            let fetched_from = (fetched.source_url != fetch_url).then_some(fetched.source_url);
+                            (fetched.content, fetched_from)
+                        }
+                        Err(e) => {
+                            error!("Error fetching full content for '{}': {}", fetch_url, e);
+                            let error_html = format!("<p style=\"color:red\"><strong>Error fetching full content:</strong> {}</p><hr/>", e);
+                            (format!("{}{}", error_html, feed_body), None)
                         }
-                    });
+                    }
+                };
+                let article_source =ArticleSource {
+                    source:source_title,
+                    position,
+                    category,
+                };
+
+                Article {
+                    title,
+                    // The page's own canonical link beats the one the feed gave.
+                    link: canonical_url.unwrap_or(link),
+                    content,
+                    pub_date,
+                    article_source,
+                    entry_id: entry.id,
+                    also_in: Vec::new(),
+                    fetched_from,
+                    author,
                 }
-            }
+            });
         }
     }
 
+    if filtered > 0 {
+        info!("Filter rules removed {} items", filtered);
+    }
+    if capped > 0 {
+        info!("Per-feed article limits skipped {} items", capped);
+    }
+
     while let Some(res) = join_set.join_next().await {
         match res {
             Ok(article) => articles.push(article),
@@ -221,3 +603,147 @@ pub async fn filter_items(
 
     articles
 }
+
+/// The entry's page link as given by the feed.
+fn entry_link(entry: &Entry) -> String {
+    entry
+        .links
+        .iter()
+        .find(|l| l.rel.as_deref() == Some("alternate") || l.rel.is_none())
+        .map(|l| l.href.clone())
+        .unwrap_or_default()
+}
+
+/// Orders a feed's selected entries by the feed's policy and keeps at most
+/// `max_articles` of them (0 keeps all).
+fn apply_article_limit(
+    entries: &mut Vec<(Entry, String, DateTime<Utc>)>,
+    max_articles: usize,
+    order: ArticleOrder,
+) {
+    match order {
+        ArticleOrder::Newest => entries.sort_by_key(|e| std::cmp::Reverse(e.2)),
+        ArticleOrder::Oldest => entries.sort_by_key(|e| e.2),
+        ArticleOrder::Feed => {}
+    }
+    if max_articles > 0 {
+        entries.truncate(max_articles);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn entries() -> Vec<(Entry, String, DateTime<Utc>)> {
+        [2, 3, 1]
+            .into_iter()
+            .map(|day| {
+                let entry = Entry { id: format!("day-{}", day), ..Default::default() };
+                (entry, String::new(), Utc.with_ymd_and_hms(2024, 1, day, 0, 0, 0).unwrap())
+            })
+            .collect()
+    }
+
+    fn ids(entries: &[(Entry, String, DateTime<Utc>)]) -> Vec<&str> {
+        entries.iter().map(|(e, _, _)| e.id.as_str()).collect()
+    }
+
+    #[test]
+    fn request_options_become_sensitive_headers() {
+        let options = FeedRequestOptions {
+            headers: vec![crate::models::RequestHeader {
+                name: "X-Api-Key".to_string(),
+                value: " abc ".to_string(),
+            }],
+            auth: FeedAuth::Basic { username: "user".to_string(), password: "pass".to_string() },
+            cookie: Some("session=1".to_string()),
+        };
+        let headers = request_headers(&options).unwrap();
+        assert_eq!(headers["x-api-key"], "abc");
+        assert_eq!(headers[header::AUTHORIZATION], "Basic dXNlcjpwYXNz");
+        assert!(headers[header::AUTHORIZATION].is_sensitive());
+        assert_eq!(headers[header::COOKIE], "session=1");
+
+        let invalid = FeedRequestOptions {
+            headers: vec![crate::models::RequestHeader { name: "Bad Name".to_string(), value: "x".to_string() }],
+            ..Default::default()
+        };
+        assert!(request_headers(&invalid).is_err());
+    }
+
+    #[tokio::test]
+    async fn feeds_from_one_host_are_fetched_politely_and_kept_in_order() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use axum::extract::Path;
+
+        // A local feed server recording how many requests it serves at once.
+        // Earlier feeds answer more slowly, so they finish out of order.
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let app = axum::Router::new().route(
+            "/{n}",
+            axum::routing::get({
+                let (in_flight, peak) = (in_flight.clone(), peak.clone());
+                move |Path(n): Path<u64>| async move {
+                    peak.fetch_max(in_flight.fetch_add(1, Ordering::SeqCst) + 1, Ordering::SeqCst);
+                    tokio::time::sleep(std::time::Duration::from_millis(20 * (6 - n))).await;
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                    format!("<rss version=\"2.0\"><channel><title>Feed {}</title></channel></rss>", n)
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let conn = crate::db::schema_init::init_db(":memory:").unwrap();
+        for n in 0..6 {
+            crate::db::feed_db::add_feed(&conn, &format!("http://{}/{}", addr, n), None, 0).unwrap();
+        }
+        let db_feeds = crate::db::feed_db::get_feeds(&conn).unwrap();
+        let db = Arc::new(Mutex::new(conn));
+        let client = HttpClient::new(&crate::util::http::HttpSettings::default()).unwrap();
+
+        let (feeds, errors) = fetch_feeds(&db_feeds, &client, &db).await;
+        assert!(errors.is_empty());
+        let titles: Vec<String> = feeds.into_iter().map(|f| f.feed.title.unwrap().content).collect();
+        assert_eq!(titles, (0..6).map(|n| format!("Feed {}", n)).collect::<Vec<_>>());
+        assert_eq!(peak.load(Ordering::SeqCst), FEED_FETCH_PER_HOST);
+    }
+
+    #[test]
+    fn undelivered_selection_keeps_a_date_bound() {
+        let day = |d| Utc.with_ymd_and_hms(2024, 3, d, 0, 0, 0).unwrap();
+        let entry = |id: &str, date: Option<DateTime<Utc>>| Entry { id: id.to_string(), published: date, ..Default::default() };
+        let mut delivered = DeliveredArticles::default();
+        delivered.entry_ids.insert("sent".to_string());
+        let selection = EntrySelection::Undelivered { delivered, since: day(10) };
+
+        assert_eq!(selection.select(&entry("new", Some(day(12))), ""), Some(day(12)));
+        assert_eq!(selection.select(&entry("sent", Some(day(12))), ""), None);
+        // Never delivered, but older than the bound: a back catalogue entry or a newly added feed's archive.
+        assert_eq!(selection.select(&entry("old", Some(day(1))), ""), None);
+        assert!(selection.select(&entry("undated", None), "").is_some());
+    }
+
+    #[test]
+    fn article_limit_respects_order_policy() {
+        let mut newest = entries();
+        apply_article_limit(&mut newest, 2, ArticleOrder::Newest);
+        assert_eq!(ids(&newest), vec!["day-3", "day-2"]);
+
+        let mut oldest = entries();
+        apply_article_limit(&mut oldest, 2, ArticleOrder::Oldest);
+        assert_eq!(ids(&oldest), vec!["day-1", "day-2"]);
+
+        let mut feed_order = entries();
+        apply_article_limit(&mut feed_order, 2, ArticleOrder::Feed);
+        assert_eq!(ids(&feed_order), vec!["day-2", "day-3"]);
+
+        let mut unlimited = entries();
+        apply_article_limit(&mut unlimited, 0, ArticleOrder::Feed);
+        assert_eq!(unlimited.len(), 3);
+    }
+}
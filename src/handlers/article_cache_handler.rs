@@ -0,0 +1,27 @@
+use std::sync::Arc;
+use axum::{
+    extract::{Query, State, Json},
+    http::StatusCode,
+};
+use crate::db::article_cache_db;
+use crate::models::{AppState, ArticleCachePurge, ArticleCachePurgeQuery};
+
+pub async fn purge_article_cache(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ArticleCachePurgeQuery>,
+) -> Result<Json<ArticleCachePurge>, (StatusCode, String)> {
+    let db = state.db.lock().map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "DB lock failed".to_string(),
+        )
+    })?;
+
+    let removed = match query.url.as_deref().map(str::trim).filter(|u| !u.is_empty()) {
+        Some(url) => article_cache_db::delete_cached_article(&db, url),
+        None => article_cache_db::clear_article_cache(&db),
+    }
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(ArticleCachePurge { removed }))
+}
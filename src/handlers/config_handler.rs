@@ -2,6 +2,7 @@ use std::sync::Arc;
 use axum::{extract::{State, Json}, http::StatusCode};
 use crate::models::{AppState, GeneralConfig};
 use crate::db;
+use crate::util::{http, urls};
 
 pub async fn get_general_config(
     State(state): State<Arc<AppState>>,
@@ -23,6 +24,35 @@ pub async fn update_general_config(
     State(state): State<Arc<AppState>>,
     Json(payload): Json<GeneralConfig>,
 ) -> Result<StatusCode, (StatusCode, String)> {
+    if !(0.0..=1.0).contains(&payload.dedup_title_similarity) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "dedup_title_similarity must be between 0 and 1".to_string(),
+        ));
+    }
+    let http_limits = [
+        payload.http_connect_timeout_seconds,
+        payload.http_read_timeout_seconds,
+        payload.http_max_response_mb,
+        payload.http_max_redirects,
+        payload.http_max_retries,
+    ];
+    if http_limits.iter().any(|v| *v < 0) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "HTTP timeouts and limits must not be negative".to_string(),
+        ));
+    }
+    if payload.article_cache_ttl_hours < 0 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "article_cache_ttl_hours must not be negative".to_string(),
+        ));
+    }
+    // Build the client before saving so a bad proxy URL is rejected.
+    http::HttpClient::new(&http::HttpSettings::from(&payload))
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid HTTP settings: {}", e)))?;
+
     let db = state.db.lock().map_err(|_| {
         (
             StatusCode::INTERNAL_SERVER_ERROR,
@@ -32,6 +62,8 @@ pub async fn update_general_config(
 
     db::update_general_config(&db, &payload)
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    http::configure(&payload).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    urls::configure_tracking_params(&payload.tracking_params);
 
     Ok(StatusCode::OK)
 }
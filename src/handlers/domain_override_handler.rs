@@ -1,12 +1,17 @@
 use std::sync::{Arc, MutexGuard};
+use std::collections::HashSet;
 use axum::{
-    extract::{Path, State, Json},
+    extract::{Multipart, Path, Query, State, Json},
     http::StatusCode,
 };
 use rusqlite::Connection;
-use crate::models::{AddDomainOverrideRequest, AppState, DomainOverride};
+use crate::models::{
+    AddDomainOverrideRequest, AppState, ContentProcessor, DomainOverride, DomainOverrideMatchQuery, FtrImportQuery,
+    FtrImportReport, FtrSkippedFile, ProcessorType,
+};
 use crate::db;
-use crate::util::content_extractors;
+use crate::util::content_extractors::{self, DomainPattern};
+use crate::util::ftr_import;
 
 pub async fn list_domain_overrides(
     State(state): State<Arc<AppState>>,
@@ -28,6 +33,32 @@ pub async fn add_domain_override(
     State(state): State<Arc<AppState>>,
     Json(payload): Json<AddDomainOverrideRequest>,
 ) -> Result<StatusCode, (StatusCode, String)> {
+    let Some(pattern) = DomainPattern::parse(&payload.domain) else {
+        return Err((StatusCode::BAD_REQUEST, format!("Invalid domain pattern: {}", payload.domain)));
+    };
+    let fallback_urls: Vec<String> = payload
+        .fallback_urls
+        .iter()
+        .map(|u| u.trim().to_string())
+        .filter(|u| !u.is_empty())
+        .collect();
+    if let Some(invalid) = fallback_urls
+        .iter()
+        .find(|u| content_extractors::expand_fallback_url(u, "https://example.com/article?id=1").is_none())
+    {
+        return Err((StatusCode::BAD_REQUEST, format!("Invalid fallback URL template: {}", invalid)));
+    }
+    let next_page_selector = payload
+        .next_page_selector
+        .as_deref()
+        .map(str::trim)
+        .filter(|s| !s.is_empty());
+    if let Some(selector) = next_page_selector
+        && !content_extractors::is_valid_selector(selector)
+    {
+        return Err((StatusCode::BAD_REQUEST, format!("Invalid next page selector: {}", selector)));
+    }
+
     let db = state.db.lock().map_err(|_| {
         (
             StatusCode::INTERNAL_SERVER_ERROR,
@@ -35,12 +66,133 @@ pub async fn add_domain_override(
         )
     })?;
 
-    db::add_domain_override(&db, &payload.domain, payload.processor, payload.custom_config.as_deref())
+    db::add_domain_override(
+        &db,
+        &pattern.to_string(),
+        &ContentProcessor {
+            id: None,
+            processor: payload.processor,
+            custom_config: payload.custom_config,
+            fallback_processors: payload.fallback_processors,
+        },
+        &fallback_urls,
+        payload.min_content_words,
+        next_page_selector,
+    )
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
     refresh_domain_processor_map(&db);
     Ok(StatusCode::CREATED)
 }
 
+/// The override that applies to `url`, or `null` when none does.
+pub async fn match_domain_override(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<DomainOverrideMatchQuery>,
+) -> Result<Json<Option<DomainOverride>>, (StatusCode, String)> {
+    if url::Url::parse(query.url.trim()).is_err() {
+        return Err((StatusCode::BAD_REQUEST, format!("Invalid URL: {}", query.url)));
+    }
+    let db = state.db.lock().map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "DB lock failed".to_string(),
+        )
+    })?;
+
+    let overrides = db::get_domain_overrides(&db)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let patterns: Vec<(usize, DomainPattern)> = overrides
+        .iter()
+        .enumerate()
+        .filter_map(|(i, o)| DomainPattern::parse(&o.domain).map(|p| (i, p)))
+        .collect();
+    let matched = content_extractors::best_matching_pattern(patterns.iter().map(|(_, p)| p), query.url.trim())
+        .map(|index| overrides[patterns[index].0].clone());
+
+    Ok(Json(matched))
+}
+
+/// Imports ftr-site-config rule files, one per `file` field, as custom extractor overrides.
+pub async fn import_ftr_site_configs(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<FtrImportQuery>,
+    mut multipart: Multipart,
+) -> Result<Json<FtrImportReport>, (StatusCode, String)> {
+    let mut report = FtrImportReport::default();
+    let mut configs = Vec::new();
+    while let Some(field) = multipart.next_field().await.map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            format!("Failed to read multipart field: {}", e),
+        )
+    })? {
+        if field.name() != Some("file") {
+            continue;
+        }
+        // Directory uploads name files by their relative path.
+        let file = field
+            .file_name()
+            .unwrap_or("")
+            .rsplit(['/', '\\'])
+            .next()
+            .unwrap_or("")
+            .to_string();
+        let data = field.bytes().await.map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to read field bytes: {}", e),
+            )
+        })?;
+        let parsed = String::from_utf8(data.to_vec())
+            .map_err(|_| "not UTF-8 text".to_string())
+            .and_then(|text| ftr_import::parse_site_config(&file, &text));
+        match parsed {
+            Ok(mut config) => {
+                report.unconverted.append(&mut config.unconverted);
+                configs.push((file, config));
+            }
+            Err(reason) => report.skipped.push(FtrSkippedFile { file, reason }),
+        }
+    }
+
+    let db = state.db.lock().map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "DB lock failed".to_string(),
+        )
+    })?;
+
+    let existing: HashSet<String> = db::get_domain_overrides(&db)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .into_iter()
+        .map(|o| o.domain)
+        .collect();
+    for (file, config) in configs {
+        let custom_config = serde_yaml::to_string(&config.extractor)
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        let processor = ContentProcessor {
+            id: None,
+            processor: ProcessorType::Custom,
+            custom_config: Some(custom_config),
+            fallback_processors: Vec::new(),
+        };
+        for pattern in config.patterns {
+            if existing.contains(&pattern) && !query.overwrite {
+                report.skipped.push(FtrSkippedFile {
+                    file: file.clone(),
+                    reason: format!("an override for {} already exists", pattern),
+                });
+                continue;
+            }
+            db::add_domain_override(&db, &pattern, &processor, &[], 0, config.next_page_selector.as_deref())
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            report.imported.push(pattern);
+        }
+    }
+    refresh_domain_processor_map(&db);
+    Ok(Json(report))
+}
+
 pub async fn delete_domain_override(
     State(state): State<Arc<AppState>>,
     Path(id): Path<i64>,
@@ -61,10 +213,15 @@ pub async fn delete_domain_override(
 fn refresh_domain_processor_map(db: &MutexGuard<Connection>) {
     if let Ok(overrides) = db::get_domain_overrides(&db) {
         let override_list: Vec<_> = overrides.into_iter()
-            .map(|o| (o.domain, crate::models::ContentProcessor {
+            .map(|o| (o.domain, ContentProcessor {
                 id: o.id,
                 processor: o.processor,
                 custom_config: o.custom_config,
+                fallback_processors: o.fallback_processors,
+            }, content_extractors::DomainFetchOptions {
+                fallback_urls: o.fallback_urls,
+                min_content_words: o.min_content_words,
+                next_page_selector: o.next_page_selector,
             }))
             .collect();
         content_extractors::refresh_domain_overrides(override_list);
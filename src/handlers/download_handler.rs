@@ -71,9 +71,9 @@ pub async fn generate_epub_adhoc(
 
     tokio::spawn(async move {
         info!("Starting background EPUB generation...");
-        match processor::generate_and_save(feeds_to_fetch, &db_clone, util::EPUB_OUTPUT_DIR, None, None).await
+        match processor::generate_and_save(feeds_to_fetch, &db_clone, util::EPUB_OUTPUT_DIR, None, None, None).await
         {
-            Ok(filename) => {
+            Ok((filename, _)) => {
                 info!("Background generation completed successfully: {}", filename);
                 match  email::check_and_send_email(db_clone, &filename, None).await {
                     Ok(_ok) => {}
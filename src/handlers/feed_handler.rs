@@ -1,9 +1,20 @@
-use crate::models::{AppState, Feed, FeedRequest, ProcessorType, ReorderFeedsRequest};
+use crate::models::{
+    AddFeedResponse, AppState, Feed, FeedHealth, FeedRequest, FeedRequestOptions, FeedSourceType, ProcessorType,
+    ReorderFeedsRequest, RequestOptionsSummary,
+};
 use axum::extract::{Multipart, Path, State};
 use axum::http::StatusCode;
 use axum::Json;
 use std::sync::Arc;
-use crate::db::{category_db, feed_db};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tracing::warn;
+use crate::db::{category_db, feed_db, feed_health_db, feed_request_options_db};
+use crate::discovery::{self, Discovery, DiscoveryError};
+use crate::util::{html_feed, http, secrets};
+
+/// Upper bound on imported OPML entries being checked at the same time.
+const OPML_DISCOVERY_CONCURRENCY: usize = 8;
 
 pub async fn list_feeds(
     State(state): State<Arc<AppState>>,
@@ -22,7 +33,34 @@ pub async fn list_feeds(
 pub async fn add_feed(
     State(state): State<Arc<AppState>>,
     Json(payload): Json<FeedRequest>,
-) -> Result<StatusCode, (StatusCode, String)> {
+) -> Result<(StatusCode, Json<AddFeedResponse>), (StatusCode, String)> {
+    validate_source(&payload)?;
+    let mut url = payload.url.trim().to_string();
+    let mut name = payload.name.clone().filter(|n| !n.trim().is_empty());
+
+    let discovered = if payload.source_type == Some(FeedSourceType::Html) {
+        None
+    } else {
+        Some(discovery::discover_feed(&http::client(), &url).await)
+    };
+    match discovered {
+        None => {}
+        Some(Ok(Discovery::Feed(feed))) => {
+            url = feed.url;
+            if name.is_none() {
+                name = feed.title;
+            }
+        }
+        Some(Ok(Discovery::Candidates(candidates))) => {
+            return Ok((
+                StatusCode::OK,
+                Json(AddFeedResponse { url, name, candidates, ..Default::default() }),
+            ));
+        }
+        Some(Err(DiscoveryError::NotFound(e))) => return Err((StatusCode::UNPROCESSABLE_ENTITY, e)),
+        Some(Err(DiscoveryError::Fetch(e))) => warn!("Could not check feed {}, storing it as given: {}", url, e),
+    }
+
     let db = state.db.lock().map_err(|_| {
         (
             StatusCode::INTERNAL_SERVER_ERROR,
@@ -32,21 +70,30 @@ pub async fn add_feed(
 
     let feed_id= feed_db::add_feed(
         &db,
-        &payload.url,
-        payload.name.as_deref(),
+        &url,
+        name.as_deref(),
         payload.concurrency_limit,
     )
     .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
     
     if let Some(processor) = payload.processor {
-        if processor != ProcessorType::Default {
-        let _ = feed_db::save_feed_processor(&db, feed_id, processor, payload.custom_config.as_deref(),);
+        if processor != ProcessorType::Default || !payload.fallback_processors.is_empty() {
+        let _ = feed_db::save_feed_processor(&db, feed_id, processor, payload.custom_config.as_deref(), &payload.fallback_processors);
         }
     }
     if let Some(category) = &payload.category {
         let _ = category_db::update_feed_category(&db, feed_id, category.id);
     }
-        Ok(StatusCode::CREATED)
+    if payload.max_articles.is_some() || payload.article_order.is_some() {
+        let _ = feed_db::save_feed_article_limit(&db, feed_id, payload.max_articles, payload.article_order);
+    }
+    if let Some(source_type) = payload.source_type {
+        let _ = feed_db::save_feed_source(&db, feed_id, source_type, payload.scrape_config.as_deref());
+    }
+    Ok((
+        StatusCode::CREATED,
+        Json(AddFeedResponse { id: Some(feed_id), url, name, candidates: Vec::new() }),
+    ))
 }
 
 pub async fn update_feed(
@@ -54,6 +101,7 @@ pub async fn update_feed(
     Path(id): Path<i64>,
     Json(payload): Json<crate::models::FeedRequest>,
 ) -> Result<StatusCode, (StatusCode, String)> {
+    validate_source(&payload)?;
     let db = state.db.lock().map_err(|_| {
         (
             StatusCode::INTERNAL_SERVER_ERROR,
@@ -71,11 +119,11 @@ pub async fn update_feed(
     .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     if let Some(processor) = payload.processor {
-        if processor == ProcessorType::Default {
+        if processor == ProcessorType::Default && payload.fallback_processors.is_empty() {
             feed_db::delete_feed_processor(&db, id)
                 .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
         } else {
-            feed_db::save_feed_processor(&db, id, processor, payload.custom_config.as_deref())
+            feed_db::save_feed_processor(&db, id, processor, payload.custom_config.as_deref(), &payload.fallback_processors)
                 .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
         }
     }
@@ -84,9 +132,25 @@ pub async fn update_feed(
         let _ = category_db::update_feed_category(&db, id, category.id);
     }
 
+    feed_db::save_feed_article_limit(&db, id, payload.max_articles, payload.article_order)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if let Some(source_type) = payload.source_type {
+        feed_db::save_feed_source(&db, id, source_type, payload.scrape_config.as_deref())
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    }
+
     Ok(StatusCode::OK)
 }
 
+/// HTML sources must come with a usable scrape config.
+fn validate_source(payload: &FeedRequest) -> Result<(), (StatusCode, String)> {
+    if payload.source_type == Some(FeedSourceType::Html) {
+        html_feed::parse_scrape_config(payload.scrape_config.as_deref().unwrap_or(""))
+            .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    }
+    Ok(())
+}
+
 pub async fn delete_feed(
     State(state): State<Arc<AppState>>,
     Path(id): Path<i64>,
@@ -101,6 +165,104 @@ pub async fn delete_feed(
     Ok(StatusCode::NO_CONTENT)
 }
 
+pub async fn save_request_options(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+    Json(payload): Json<FeedRequestOptions>,
+) -> Result<Json<RequestOptionsSummary>, (StatusCode, String)> {
+    crate::feed::request_headers(&payload).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    if !secrets::has_secret_key() {
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            format!("Set {} on the server to store feed request options", secrets::SECRET_KEY_ENV),
+        ));
+    }
+    let json = serde_json::to_string(&payload)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let sealed = secrets::seal(&json).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let summary = payload.summary();
+
+    let db = state.db.lock().map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "DB lock failed".to_string(),
+        )
+    })?;
+    let saved = feed_request_options_db::save_request_options(&db, id, &sealed, &summary)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if !saved {
+        return Err((StatusCode::NOT_FOUND, "Feed not found".to_string()));
+    }
+    Ok(Json(summary))
+}
+
+pub async fn delete_request_options(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let db = state.db.lock().map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "DB lock failed".to_string(),
+        )
+    })?;
+    let deleted = feed_request_options_db::delete_request_options(&db, id)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if deleted == 0 {
+        return Err((StatusCode::NOT_FOUND, "No request options stored for feed".to_string()));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn list_feed_health(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<FeedHealth>>, (StatusCode, String)> {
+    let db = state.db.lock().map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "DB lock failed".to_string(),
+        )
+    })?;
+    let health = feed_health_db::get_all_feed_health(&db)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(health))
+}
+
+pub async fn get_feed_health(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> Result<Json<FeedHealth>, (StatusCode, String)> {
+    let db = state.db.lock().map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "DB lock failed".to_string(),
+        )
+    })?;
+    let health = feed_health_db::get_feed_health(&db, id)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Feed not found".to_string()))?;
+    Ok(Json(health))
+}
+
+pub async fn resume_feed(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let db = state.db.lock().map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "DB lock failed".to_string(),
+        )
+    })?;
+    let updated = feed_health_db::resume_feed(&db, id)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if updated == 0 {
+        return Err((StatusCode::NOT_FOUND, "Feed not found".to_string()));
+    }
+    Ok(StatusCode::OK)
+}
+
 pub async fn reorder_feeds(
     State(state): State<Arc<AppState>>,
     Json(payload): Json<ReorderFeedsRequest>,
@@ -151,6 +313,20 @@ pub async fn import_opml(
                 )
             })?;
 
+            let mut outlines = Vec::new();
+            for outline in document.body.outlines {
+                if let Some(xml_url) = outline.xml_url {
+                    outlines.push((xml_url, outline.text));
+                }
+                for child in outline.outlines {
+                    if let Some(xml_url) = child.xml_url {
+                        outlines.push((xml_url, child.text));
+                    }
+                }
+            }
+
+            let resolved = resolve_imported_feeds(outlines).await;
+
             let db = state.db.lock().map_err(|_| {
                 (
                     StatusCode::INTERNAL_SERVER_ERROR,
@@ -158,18 +334,8 @@ pub async fn import_opml(
                 )
             })?;
 
-            for outline in document.body.outlines {
-                if let Some(xml_url) = outline.xml_url {
-                    let _ = feed_db::add_feed(&db, &xml_url, Some(&outline.text), 0);
-                }
-
-                if !outline.outlines.is_empty() {
-                    for child in outline.outlines {
-                        if let Some(xml_url) = child.xml_url {
-                            let _ = feed_db::add_feed(&db, &xml_url, Some(&child.text), 0);
-                        }
-                    }
-                }
+            for (url, name) in resolved {
+                let _ = feed_db::add_feed(&db, &url, name.as_deref(), 0);
             }
 
             return Ok(StatusCode::CREATED);
@@ -177,4 +343,43 @@ pub async fn import_opml(
     }
 
     Err((StatusCode::BAD_REQUEST, "No file field found".to_string()))
-}
\ No newline at end of file
+}
+
+/// Runs feed discovery for imported OPML entries so links to web pages are
+/// replaced by the feed they advertise. Entries that cannot be checked are kept
+/// as given.
+async fn resolve_imported_feeds(outlines: Vec<(String, String)>) -> Vec<(String, Option<String>)> {
+    let client = http::client();
+    let semaphore = Arc::new(Semaphore::new(OPML_DISCOVERY_CONCURRENCY));
+    let mut join_set = JoinSet::new();
+
+    for (idx, (url, text)) in outlines.into_iter().enumerate() {
+        let client = client.clone();
+        let semaphore = semaphore.clone();
+        join_set.spawn(async move {
+            let _permit = semaphore.acquire_owned().await;
+            let name = Some(text).filter(|t| !t.trim().is_empty());
+            let resolved = match discovery::discover_feed(&client, &url).await {
+                Ok(Discovery::Feed(feed)) => (feed.url, name.or(feed.title)),
+                Ok(Discovery::Candidates(mut candidates)) => {
+                    let feed = candidates.remove(0);
+                    (feed.url, name.or(feed.title))
+                }
+                Err(e) => {
+                    warn!("Could not check imported feed {}, storing it as given: {}", url, e);
+                    (url, name)
+                }
+            };
+            (idx, resolved)
+        });
+    }
+
+    let mut resolved = Vec::new();
+    while let Some(res) = join_set.join_next().await {
+        if let Ok(item) = res {
+            resolved.push(item);
+        }
+    }
+    resolved.sort_by_key(|(idx, _)| *idx);
+    resolved.into_iter().map(|(_, item)| item).collect()
+}
@@ -0,0 +1,77 @@
+use std::sync::Arc;
+use axum::{
+    extract::{Path, State, Json},
+    http::StatusCode,
+};
+use crate::db::filter_rule_db;
+use crate::entry_filter;
+use crate::models::{AppState, FilterRule};
+
+pub async fn list_filter_rules(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<FilterRule>>, (StatusCode, String)> {
+    let db = state.db.lock().map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "DB lock failed".to_string(),
+        )
+    })?;
+
+    let rules = filter_rule_db::get_filter_rules(&db)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(rules))
+}
+
+pub async fn add_filter_rule(
+    State(state): State<Arc<AppState>>,
+    Json(mut payload): Json<FilterRule>,
+) -> Result<(StatusCode, Json<FilterRule>), (StatusCode, String)> {
+    if payload.feed_id.is_some() == payload.category_id.is_some() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Exactly one of feed_id or category_id is required".to_string(),
+        ));
+    }
+    entry_filter::validate_rule(&payload).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    let db = state.db.lock().map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "DB lock failed".to_string(),
+        )
+    })?;
+
+    let target_exists = filter_rule_db::rule_target_exists(&db, &payload)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if !target_exists {
+        let target = if payload.feed_id.is_some() { "Feed" } else { "Category" };
+        return Err((StatusCode::BAD_REQUEST, format!("{} not found", target)));
+    }
+
+    let id = filter_rule_db::add_filter_rule(&db, &payload)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    payload.id = Some(id);
+
+    Ok((StatusCode::CREATED, Json(payload)))
+}
+
+pub async fn delete_filter_rule(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let db = state.db.lock().map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "DB lock failed".to_string(),
+        )
+    })?;
+
+    let deleted = filter_rule_db::delete_filter_rule(&db, id)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if deleted == 0 {
+        return Err((StatusCode::NOT_FOUND, "Filter rule not found".to_string()));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
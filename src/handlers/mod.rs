@@ -8,6 +8,7 @@ use axum::{
 use tokio::io::AsyncWriteExt;
 use tracing::{info};
 
+pub mod article_cache_handler;
 pub mod download_handler;
 pub mod feed_handler;
 pub mod schedule_handler;
@@ -17,6 +18,8 @@ pub mod email_handler;
 pub mod config_handler;
 pub mod domain_override_handler;
 pub mod category_handler;
+pub mod filter_rule_handler;
+pub mod preview_handler;
 
 pub async fn opds_handler(headers: HeaderMap) -> Result<impl IntoResponse, (StatusCode, String)> {
     let host = headers
@@ -0,0 +1,161 @@
+use std::sync::Arc;
+use axum::{extract::{Json, State}, http::StatusCode};
+use dom_query::Document;
+use reqwest::header::HeaderMap;
+use crate::db;
+use crate::models::{AppState, ContentProcessor, ExtractionPreview, ExtractionPreviewRequest};
+use crate::util::{self, content_extractors, http};
+use crate::util::content_extractors::DomainFetchOptions;
+
+/// Runs the extraction pipeline on a URL and returns what would end up in the
+/// EPUB. Nothing is saved; extraction problems are reported in `errors`.
+pub async fn preview_extraction(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<ExtractionPreviewRequest>,
+) -> Result<Json<ExtractionPreview>, (StatusCode, String)> {
+    let url = payload.url.trim();
+    let saved = (
+        content_extractors::get_domain_override(url).map(|p| (*p).clone()),
+        content_extractors::get_domain_fetch_options(url).map(|o| (*o).clone()).unwrap_or_default(),
+    );
+    preview_with_saved_override(&state, payload, saved).await
+}
+
+/// [`preview_extraction`] with the saved domain override for the URL passed in,
+/// which a draft in the request replaces.
+async fn preview_with_saved_override(
+    state: &AppState,
+    payload: ExtractionPreviewRequest,
+    saved: (Option<ContentProcessor>, DomainFetchOptions),
+) -> Result<Json<ExtractionPreview>, (StatusCode, String)> {
+    let url = payload.url.trim();
+    if url::Url::parse(url).is_err() {
+        return Err((StatusCode::BAD_REQUEST, format!("Invalid URL: {}", url)));
+    }
+    let html_profile = {
+        let db = state.db.lock().map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "DB lock failed".to_string(),
+            )
+        })?;
+        db::get_general_config(&db)
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+            .html_profile
+    };
+
+    let (processor, options) = match payload.domain_override {
+        Some(draft) => {
+            if let Some(selector) = draft.next_page_selector.as_deref().filter(|s| !s.trim().is_empty())
+                && !content_extractors::is_valid_selector(selector)
+            {
+                return Err((StatusCode::BAD_REQUEST, format!("Invalid next page selector: {}", selector)));
+            }
+            let processor = ContentProcessor {
+                id: None,
+                processor: draft.processor,
+                custom_config: draft.custom_config,
+                fallback_processors: draft.fallback_processors,
+            };
+            let options = DomainFetchOptions {
+                fallback_urls: draft.fallback_urls.into_iter().filter(|u| !u.trim().is_empty()).collect(),
+                min_content_words: draft.min_content_words,
+                next_page_selector: draft.next_page_selector.filter(|s| !s.trim().is_empty()),
+            };
+            (Some(processor), options)
+        }
+        None => {
+            let (saved_processor, saved_options) = saved;
+            (payload.processor.or(saved_processor), saved_options)
+        }
+    };
+
+    let client = http::client();
+    let mut preview = ExtractionPreview::default();
+    match content_extractors::fetch_content(&client, url, processor.as_ref(), &options, &HeaderMap::new(), None).await {
+        Ok(fetched) => {
+            let html = util::fix_xhtml(&util::clean_html(&util::normalize_images(&fetched.content), html_profile));
+            let doc = Document::from(html.as_str());
+            let mut images: Vec<String> = Vec::new();
+            for img in doc.select("img[src]").nodes() {
+                if let Some(src) = img.attr("src")
+                    && !images.iter().any(|i| i.as_str() == &*src)
+                {
+                    images.push(src.to_string());
+                }
+            }
+
+            preview.title = fetched.title;
+            preview.author = fetched.metadata.author;
+            preview.published = fetched.metadata.published.map(|d| d.to_rfc3339());
+            preview.lead_image = fetched.metadata.lead_image;
+            preview.word_count = content_extractors::word_count(&html);
+            preview.source_url = Some(fetched.source_url);
+            preview.pages = fetched.pages;
+            preview.images = images;
+            preview.errors = fetched.warnings;
+            preview.html = html;
+        }
+        Err(e) => preview.errors.push(e.to_string()),
+    }
+
+    Ok(Json(preview))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use crate::models::{AddDomainOverrideRequest, ProcessorType};
+
+    fn custom(config: &str) -> ContentProcessor {
+        ContentProcessor {
+            id: None,
+            processor: ProcessorType::Custom,
+            custom_config: Some(config.to_string()),
+            fallback_processors: Vec::new(),
+        }
+    }
+
+    fn draft(config: &str, next_page_selector: Option<&str>) -> AddDomainOverrideRequest {
+        AddDomainOverrideRequest {
+            domain: "127.0.0.1".to_string(),
+            processor: ProcessorType::Custom,
+            custom_config: Some(config.to_string()),
+            fallback_processors: Vec::new(),
+            fallback_urls: Vec::new(),
+            min_content_words: 0,
+            next_page_selector: next_page_selector.map(str::to_string),
+        }
+    }
+
+    #[tokio::test]
+    async fn draft_override_replaces_saved_one_and_is_validated() {
+        let page = "<html><head><title>Page</title></head><body><h1>Saved title</h1><h2>Draft title</h2>\
+                    <article><p>Body text.</p></article></body></html>";
+        let app = axum::Router::new().route("/story", axum::routing::get(move || async move { axum::response::Html(page) }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/story", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let state = AppState {
+            db: Arc::new(Mutex::new(db::schema_init::init_db(":memory:").unwrap())),
+            scheduler: Arc::new(tokio::sync::Mutex::new(tokio_cron_scheduler::JobScheduler::new().await.unwrap())),
+        };
+        let saved = || (Some(custom("selector: [article]\ntitle: h1")), DomainFetchOptions::default());
+        let preview = |domain_override| {
+            let request = ExtractionPreviewRequest { url: url.clone(), processor: None, domain_override };
+            preview_with_saved_override(&state, request, saved())
+        };
+
+        let saved = preview(None).await.unwrap().0;
+        assert_eq!(saved.title, "Saved title");
+        assert!(saved.errors.is_empty());
+        let drafted = preview(Some(draft("selector: [article]\ntitle: h2", None))).await.unwrap().0;
+        assert_eq!(drafted.title, "Draft title");
+
+        let (status, message) = preview(Some(draft("selector: [article]", Some(".pager >> a")))).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(message.contains(".pager >> a"));
+    }
+}
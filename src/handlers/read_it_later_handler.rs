@@ -2,6 +2,7 @@ use crate::models::{
     AddReadItLaterRequest, AppState, ReadItLaterArticle, UpdateReadItLaterStatusRequest,
 };
 use crate::{db, email, processor, util};
+use crate::util::article_cache::ArticleCache;
 use axum::Json;
 use axum::extract::{Path, State};
 use axum::http::StatusCode;
@@ -35,7 +36,7 @@ pub async fn add_read_it_later(
     })?;
     let is_valid = is_valid_web_url(&payload.url);
     if is_valid {
-        db::add_read_it_later_article(&db, &payload.url)
+        db::add_read_it_later_article(&db, &util::urls::strip_tracking_params(&payload.url))
             .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
     } else {
         return Ok(StatusCode::BAD_REQUEST);
@@ -108,28 +109,29 @@ pub async fn deliver_read_it_later(
     let db_clone = state.db.clone();
     tokio::spawn(async move {
         info!("Starting background Read It Later EPUB generation...");
-        let (image_timeout, cover_text) = {
+        let (epub_options, cache) = {
             match db_clone.lock() {
                 Ok(conn) => match db::get_general_config(&conn) {
                     Ok(cfg) => {
-                        let cover_text = processor::cover_text_config_from_general_config(
+                        let epub_options = processor::epub_options_from_general_config(
                             &cfg,
                             Some(processor::READ_IT_LATER_COVER_TEXT_CONTEXT.to_string()),
                         );
-                        (cfg.image_timeout_seconds, cover_text)
+                        let cache = ArticleCache::new(db_clone.clone(), &cfg);
+                        (epub_options, cache)
                     }
                     Err(e) => {
                         tracing::error!("Failed to fetch config, using default timeout: {}", e);
-                        (45, Default::default())
+                        (Default::default(), None)
                     }
                 },
                 Err(_) => {
                     tracing::error!("Failed to lock DB for config, using default timeout");
-                    (45, Default::default())
+                    (Default::default(), None)
                 }
             }
         };
-        match processor::generate_read_it_later_epub(articles, util::EPUB_OUTPUT_DIR, image_timeout, cover_text)
+        match processor::generate_read_it_later_epub(articles, util::EPUB_OUTPUT_DIR, epub_options, cache)
             .await
         {
             Ok(filename) => {
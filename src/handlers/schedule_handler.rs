@@ -6,7 +6,7 @@ use chrono_tz::Tz;
 use chrono::{Local, Timelike, Datelike};
 use tracing::{info, warn};
 use crate::{db, email, scheduler};
-use crate::models::{AddScheduleRequest, AppState, ScheduleResponse};
+use crate::models::{AddScheduleRequest, AppState, ArticleSelection, Schedule, ScheduleResponse};
 
 pub async fn list_schedules(
     State(state): State<Arc<AppState>>,
@@ -38,6 +38,7 @@ pub async fn list_schedules(
                             category_ids: s.category_ids.clone(),
                             override_to_email: s.override_to_email.clone(),
                             fetch_since_hours_override: s.fetch_since_hours_override,
+                            article_selection: s.article_selection,
                         });
                         continue;
                     }
@@ -59,11 +60,12 @@ pub async fn add_schedule(
     Json(payload): Json<AddScheduleRequest>,
 ) -> Result<StatusCode, (StatusCode, String)> {
     let cron_expression = build_cron_expression(&payload)?;
-    let override_to_email = validate_override_email(payload.override_to_email)?;
     let fetch_since_hours_override = validate_fetch_since_hours_override(
         &payload.schedule_type,
         payload.fetch_since_hours_override,
     )?;
+    validate_article_selection(&payload, fetch_since_hours_override)?;
+    let override_to_email = validate_override_email(payload.override_to_email)?;
 
     info!(
         "Converting {} {:02}:{:02} -> Cron {}",
@@ -77,15 +79,18 @@ pub async fn add_schedule(
                 "DB lock failed".to_string(),
             )
         })?;
-        db::add_schedule(
-            &db,
-            &cron_expression,
-            &payload.schedule_type,
-            &payload.timezone,
-            &payload.category_ids,
-            override_to_email.as_deref(),
+        let schedule = Schedule {
+            id: None,
+            cron_expression,
+            active: true,
+            schedule_type: payload.schedule_type,
+            timezone: payload.timezone,
+            category_ids: payload.category_ids,
+            override_to_email,
             fetch_since_hours_override,
-        )
+            article_selection: payload.article_selection,
+        };
+        db::add_schedule(&db, &schedule)
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
     }
 
@@ -102,11 +107,12 @@ pub async fn update_schedule(
     Json(payload): Json<AddScheduleRequest>,
 ) -> Result<StatusCode, (StatusCode, String)> {
     let cron_expression = build_cron_expression(&payload)?;
-    let override_to_email = validate_override_email(payload.override_to_email)?;
     let fetch_since_hours_override = validate_fetch_since_hours_override(
         &payload.schedule_type,
         payload.fetch_since_hours_override,
     )?;
+    validate_article_selection(&payload, fetch_since_hours_override)?;
+    let override_to_email = validate_override_email(payload.override_to_email)?;
 
     {
         let db = state.db.lock().map_err(|_| {
@@ -115,16 +121,18 @@ pub async fn update_schedule(
                 "DB lock failed".to_string(),
             )
         })?;
-        db::update_schedule(
-            &db,
-            id,
-            &cron_expression,
-            &payload.schedule_type,
-            &payload.timezone,
-            &payload.category_ids,
-            override_to_email.as_deref(),
+        let schedule = Schedule {
+            id: Some(id),
+            cron_expression,
+            active: true,
+            schedule_type: payload.schedule_type,
+            timezone: payload.timezone,
+            category_ids: payload.category_ids,
+            override_to_email,
             fetch_since_hours_override,
-        )
+            article_selection: payload.article_selection,
+        };
+        db::update_schedule(&db, id, &schedule)
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
     }
 
@@ -223,6 +231,28 @@ fn validate_fetch_since_hours_override(
     }
 }
 
+fn validate_article_selection(
+    payload: &AddScheduleRequest,
+    fetch_since_hours_override: Option<i32>,
+) -> Result<(), (StatusCode, String)> {
+    if payload.article_selection != ArticleSelection::Undelivered {
+        return Ok(());
+    }
+    if payload.schedule_type != "rss" {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "article_selection undelivered is only supported for rss schedules".to_string(),
+        ));
+    }
+    if fetch_since_hours_override.is_some() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "fetch_since_hours_override cannot be combined with article_selection undelivered".to_string(),
+        ));
+    }
+    Ok(())
+}
+
 fn validate_override_email(
     override_to_email: Option<String>,
 ) -> Result<Option<String>, (StatusCode, String)> {
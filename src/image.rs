@@ -2,13 +2,13 @@ use anyhow::Context;
 use anyhow::Result;
 use libvips::{ops, VipsImage};
 use regex::Regex;
-use reqwest::Client;
 use std::io::Write;
 use std::sync::Arc;
 use tempfile::NamedTempFile;
 use tokio::sync::Semaphore;
 use tokio::task::JoinSet;
 use tracing::{error, info, warn};
+use crate::util::http::{self, HttpClient};
 
 pub async fn process_images(html: &str) -> (String, Vec<(String, NamedTempFile, String)>) {
     let mut processed_html = html.to_string();
@@ -16,10 +16,7 @@ pub async fn process_images(html: &str) -> (String, Vec<(String, NamedTempFile,
 
     let img_regex = Regex::new(r#"<img[^>]+src="([^"]+)"[^>]*>"#).unwrap();
 
-    let client = Client::builder()
-        .user_agent("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")
-        .build()
-        .unwrap_or_else(|_| Client::new());
+    let client = http::client();
 
     let mut matches = Vec::new();
     for cap in img_regex.captures_iter(html) {
@@ -90,11 +87,14 @@ pub async fn process_images(html: &str) -> (String, Vec<(String, NamedTempFile,
     (processed_html, images)
 }
 
-async fn download_image(client: &Client, url: &str) -> Result<NamedTempFile> {
-    let mut resp = client.get(url).send().await?;
+async fn download_image(client: &HttpClient, url: &str) -> Result<NamedTempFile> {
+    let mut resp = client.send(client.get(url)).await?;
     let mut temp_file = NamedTempFile::new()?;
 
+    let mut received = 0;
     while let Some(chunk) = resp.chunk().await? {
+        received += chunk.len();
+        client.check_size(received)?;
         temp_file.write_all(&chunk)?;
     }
 
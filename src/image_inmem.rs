@@ -2,7 +2,6 @@ use crate::models::epub_message::{CompletionMessage, EpubPart};
 use anyhow::Result;
 use image::ImageFormat;
 use regex::Regex;
-use reqwest::Client;
 use std::any::Any;
 use std::io::Cursor;
 use std::sync::LazyLock;
@@ -10,6 +9,7 @@ use std::time::Duration;
 use tokio::sync::mpsc::Sender;
 use tracing::{error, info};
 use uuid::Uuid;
+use crate::util::http::{self, HttpClient};
 
 pub async fn process_images(
     html: &str,
@@ -22,11 +22,8 @@ pub async fn process_images(
     static IMG_REGEX: LazyLock<Regex> =
         LazyLock::new(|| Regex::new(r#"<img[^>]+src="([^"]+)"[^>]*>"#).unwrap());
 
-    let client = Client::builder()
-        .user_agent("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")
-        .timeout(Duration::from_secs(timeout_seconds))
-        .build()
-        .unwrap_or_else(|_| Client::new());
+    let client = http::client();
+    let timeout = Duration::from_secs(timeout_seconds);
 
     let mut matches = Vec::new();
     //TODO: compare performance regex vs dom_query
@@ -50,7 +47,7 @@ pub async fn process_images(
         let sq = *seq_id;
         tokio::spawn(async move {
             info!("Processing image: {}", src_clone);
-            match download_image(&client, &src_clone).await
+            match download_image(&client, &src_clone, timeout).await
             {
                     Ok((img_data, format)) => match resize_and_grayscale(img_data, format).await {
                         Ok(processed_data) => {
@@ -107,10 +104,10 @@ async fn send_empty_message_on_error(i: usize, tx_m: Sender<CompletionMessage>,
     return Err("Failed");
 }
 
-async fn download_image(client: &Client, url: &str) -> Result<(Vec<u8>, ImageFormat)> {
-    let resp = client.get(url).send().await?;
+async fn download_image(client: &HttpClient, url: &str, timeout: Duration) -> Result<(Vec<u8>, ImageFormat)> {
+    let resp = client.send(client.get(url).timeout(timeout)).await?;
     //let _content_length = &resp.content_length().unwrap_or(0);
-    let bytes = resp.bytes().await?.to_vec();
+    let bytes = client.bytes(resp).await?;
 
     //info!("Image size is {}  {}", content_length, &bytes.capacity());
 
@@ -2,7 +2,10 @@
 // This file re-exports the necessary modules for testing
 
 pub mod db;
+pub mod dedup;
+pub mod discovery;
 pub mod email;
+pub mod entry_filter;
 pub mod epub_gen;
 pub mod feed;
 #[cfg(feature = "mem_opt")]
@@ -1,5 +1,8 @@
 mod db;
+mod dedup;
+mod discovery;
 mod email;
+mod entry_filter;
 mod epub_gen;
 mod feed;
 #[cfg(feature = "mem_opt")]
@@ -43,6 +46,14 @@ async fn main() {
         .init();
 
     let conn = schema_init::init_db("./db/rpub.db").expect("Failed to initialize database");
+    match db::get_general_config(&conn).map(|config| {
+        util::urls::configure_tracking_params(&config.tracking_params);
+        util::http::configure(&config)
+    }) {
+        Ok(Err(e)) => tracing::error!("Invalid HTTP settings, using defaults: {}", e),
+        Err(e) => tracing::error!("Failed to load general config: {}", e),
+        Ok(Ok(())) => {}
+    }
     let db_mutex = Arc::new(Mutex::new(conn));
     let sched = scheduler::init_scheduler(db_mutex.clone())
         .await
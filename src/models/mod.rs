@@ -1,4 +1,6 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::sync::{Arc, Mutex};
 use tokio::sync::Mutex as TokioMutex;
 use tokio_cron_scheduler::JobScheduler;
@@ -19,6 +21,160 @@ pub struct Feed {
     pub category_id: Option<i64>,
     #[serde(default)]
     pub category: Option<String>,
+    #[serde(default)]
+    pub paused: bool,
+    #[serde(default)]
+    pub filter_rules: Vec<FilterRule>,
+    /// Most articles this feed may contribute to a digest; 0 means no limit.
+    #[serde(default)]
+    pub max_articles: usize,
+    #[serde(default)]
+    pub article_order: ArticleOrder,
+    #[serde(default)]
+    pub source_type: FeedSourceType,
+    /// YAML [`HtmlScrapeConfig`] for `html` sources.
+    #[serde(default)]
+    pub scrape_config: Option<String>,
+    /// Which request options are stored for the feed; the values are never returned.
+    #[serde(default)]
+    pub request_options: Option<RequestOptionsSummary>,
+}
+
+/// Extra request settings for feeds behind authentication, sent with the feed
+/// fetch and with article fetches on the feed's host. Write-only over the API.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct FeedRequestOptions {
+    #[serde(default)]
+    pub headers: Vec<RequestHeader>,
+    #[serde(default)]
+    pub auth: FeedAuth,
+    #[serde(default)]
+    pub cookie: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RequestHeader {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum FeedAuth {
+    #[default]
+    None,
+    Basic { username: String, password: String },
+    Bearer { token: String },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct RequestOptionsSummary {
+    pub header_names: Vec<String>,
+    /// `basic` or `bearer` when credentials are stored.
+    pub auth: Option<String>,
+    pub cookie: bool,
+}
+
+impl FeedRequestOptions {
+    pub fn summary(&self) -> RequestOptionsSummary {
+        RequestOptionsSummary {
+            header_names: self.headers.iter().map(|h| h.name.clone()).collect(),
+            auth: match self.auth {
+                FeedAuth::None => None,
+                FeedAuth::Basic { .. } => Some("basic".to_string()),
+                FeedAuth::Bearer { .. } => Some("bearer".to_string()),
+            },
+            cookie: self.cookie.as_deref().is_some_and(|c| !c.trim().is_empty()),
+        }
+    }
+}
+
+/// Where a feed's entries come from: a real RSS/Atom/JSON feed, or a web page
+/// listing articles that is scraped with CSS selectors.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum FeedSourceType {
+    #[default]
+    Feed,
+    Html,
+}
+
+impl FeedSourceType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Feed => "feed",
+            Self::Html => "html",
+        }
+    }
+
+    pub fn from_db(value: &str) -> Self {
+        match value {
+            "html" => Self::Html,
+            _ => Self::Feed,
+        }
+    }
+}
+
+/// Which of a feed's entries are kept when `max_articles` applies.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ArticleOrder {
+    #[default]
+    Newest,
+    Oldest,
+    /// The order the entries appear in the feed.
+    Feed,
+}
+
+impl ArticleOrder {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Newest => "newest",
+            Self::Oldest => "oldest",
+            Self::Feed => "feed",
+        }
+    }
+
+    pub fn from_db(value: &str) -> Self {
+        match value {
+            "oldest" => Self::Oldest,
+            "feed" => Self::Feed,
+            _ => Self::Newest,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct FeedHealth {
+    pub feed_id: i64,
+    pub paused: bool,
+    pub last_success_at: Option<String>,
+    pub last_error_at: Option<String>,
+    pub last_error: Option<String>,
+    pub last_http_status: Option<u16>,
+    pub consecutive_failures: i64,
+    pub total_fetches: i64,
+    pub avg_fetch_ms: f64,
+}
+
+pub struct FeedHttpCache {
+    pub url: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub parsed_feed: String,
+}
+
+/// Extracted article stored in the article cache.
+pub struct CachedArticle {
+    pub url: String,
+    pub title: String,
+    pub content: String,
+    pub source_url: String,
+    pub pages: usize,
+    pub author: Option<String>,
+    pub published: Option<DateTime<Utc>>,
+    pub lead_image: Option<String>,
+    pub canonical_url: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -31,6 +187,110 @@ pub struct Category {
     pub id: Option<i64>,
     pub name: String,
     pub position: i64,
+    #[serde(default)]
+    pub filter_rules: Vec<FilterRule>,
+}
+
+/// Include/exclude rule applied to feed entries before any article is fetched.
+/// Belongs to either a feed or a category.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FilterRule {
+    pub id: Option<i64>,
+    #[serde(default)]
+    pub feed_id: Option<i64>,
+    #[serde(default)]
+    pub category_id: Option<i64>,
+    pub action: FilterAction,
+    #[serde(default)]
+    pub match_type: FilterMatchType,
+    #[serde(default)]
+    pub field: FilterField,
+    pub pattern: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FilterAction {
+    /// Keep only entries matching at least one include rule.
+    Include,
+    /// Drop entries matching any exclude rule.
+    Exclude,
+}
+
+impl FilterAction {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Include => "include",
+            Self::Exclude => "exclude",
+        }
+    }
+
+    pub fn from_db(value: &str) -> Self {
+        match value {
+            "include" => Self::Include,
+            _ => Self::Exclude,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum FilterMatchType {
+    /// Case-insensitive substring match.
+    #[default]
+    Keyword,
+    Regex,
+}
+
+impl FilterMatchType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Keyword => "keyword",
+            Self::Regex => "regex",
+        }
+    }
+
+    pub fn from_db(value: &str) -> Self {
+        match value {
+            "regex" => Self::Regex,
+            _ => Self::Keyword,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum FilterField {
+    #[default]
+    Title,
+    Author,
+    /// The entry's categories/tags.
+    Tags,
+    Summary,
+    /// Any of the above.
+    Any,
+}
+
+impl FilterField {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Title => "title",
+            Self::Author => "author",
+            Self::Tags => "tags",
+            Self::Summary => "summary",
+            Self::Any => "any",
+        }
+    }
+
+    pub fn from_db(value: &str) -> Self {
+        match value {
+            "author" => Self::Author,
+            "tags" => Self::Tags,
+            "summary" => Self::Summary,
+            "any" => Self::Any,
+            _ => Self::Title,
+        }
+    }
 }
 
 #[derive(Deserialize)]
@@ -64,6 +324,49 @@ pub struct Schedule {
     pub override_to_email: Option<String>,
     #[serde(default)]
     pub fetch_since_hours_override: Option<i32>,
+    #[serde(default)]
+    pub article_selection: ArticleSelection,
+}
+
+/// How an rss schedule picks the entries that go into its digest.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ArticleSelection {
+    /// Entries published within the fetch window.
+    #[default]
+    Window,
+    /// Entries this schedule has not delivered before, back to the fetch window
+    /// before its last delivery (or before now, until it has delivered anything).
+    Undelivered,
+}
+
+impl ArticleSelection {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Window => "window",
+            Self::Undelivered => "undelivered",
+        }
+    }
+
+    pub fn from_db(value: &str) -> Self {
+        match value {
+            "undelivered" => Self::Undelivered,
+            _ => Self::Window,
+        }
+    }
+}
+
+/// Entry ids and links already delivered by a schedule.
+#[derive(Debug, Clone, Default)]
+pub struct DeliveredArticles {
+    pub entry_ids: HashSet<String>,
+    pub links: HashSet<String>,
+}
+
+impl DeliveredArticles {
+    pub fn contains(&self, entry_id: &str, link: &str) -> bool {
+        self.entry_ids.contains(entry_id) || (!link.is_empty() && self.links.contains(link))
+    }
 }
 
 fn default_schedule_type() -> String {
@@ -108,7 +411,35 @@ pub struct FeedRequest {
     pub processor: Option<ProcessorType>,
     pub custom_config: Option<String>,
     #[serde(default)]
+    pub fallback_processors: Vec<ProcessorType>,
+    #[serde(default)]
     pub category: Option<CategoryId>,
+    /// Left unchanged on update when omitted.
+    #[serde(default)]
+    pub max_articles: Option<usize>,
+    #[serde(default)]
+    pub article_order: Option<ArticleOrder>,
+    /// When set, `scrape_config` is stored along with it.
+    #[serde(default)]
+    pub source_type: Option<FeedSourceType>,
+    #[serde(default)]
+    pub scrape_config: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct FeedCandidate {
+    pub url: String,
+    pub title: Option<String>,
+}
+
+/// Result of `POST /feeds`. When the URL was a web page advertising several
+/// feeds nothing is stored and `candidates` lists them for the user to pick.
+#[derive(Serialize, Debug, Default)]
+pub struct AddFeedResponse {
+    pub id: Option<i64>,
+    pub url: String,
+    pub name: Option<String>,
+    pub candidates: Vec<FeedCandidate>,
 }
 
 #[derive(Deserialize)]
@@ -133,6 +464,7 @@ pub struct ScheduleResponse {
     pub category_ids: Vec<i64>,
     pub override_to_email: Option<String>,
     pub fetch_since_hours_override: Option<i32>,
+    pub article_selection: ArticleSelection,
 }
 
 #[derive(Deserialize)]
@@ -153,6 +485,8 @@ pub struct AddScheduleRequest {
     pub override_to_email: Option<String>,
     #[serde(default)]
     pub fetch_since_hours_override: Option<i32>,
+    #[serde(default)]
+    pub article_selection: ArticleSelection,
 }
 
 fn default_frequency() -> String {
@@ -189,6 +523,73 @@ pub struct GeneralConfig {
     pub cover_text_position: CoverTextPosition,
     #[serde(default = "default_cover_text_size")]
     pub cover_text_size: CoverTextSize,
+    /// Pause a feed after this many consecutive fetch failures; 0 disables auto-pausing.
+    #[serde(default)]
+    pub auto_pause_after_failures: i32,
+    /// Drop articles whose normalized link was already included from another feed.
+    #[serde(default = "default_true")]
+    pub dedup_articles: bool,
+    /// Also treat articles as duplicates when their titles' word overlap reaches this
+    /// ratio (0-1); 0 disables title matching.
+    #[serde(default)]
+    pub dedup_title_similarity: f64,
+    /// User agent for outgoing requests; empty uses the built-in browser user agent.
+    #[serde(default)]
+    pub http_user_agent: String,
+    /// `http://`, `https://`, `socks5://` or `socks5h://` proxy URL; empty for none.
+    #[serde(default)]
+    pub http_proxy: String,
+    #[serde(default = "default_connect_timeout")]
+    pub http_connect_timeout_seconds: i32,
+    #[serde(default = "default_timeout")]
+    pub http_read_timeout_seconds: i32,
+    /// Largest response body that is read, in megabytes; 0 for no limit.
+    #[serde(default = "default_max_response_mb")]
+    pub http_max_response_mb: i32,
+    #[serde(default = "default_max_redirects")]
+    pub http_max_redirects: i32,
+    #[serde(default = "default_max_retries")]
+    pub http_max_retries: i32,
+    /// How long extracted articles are reused instead of fetched again; 0 disables the cache.
+    #[serde(default = "default_article_cache_ttl_hours")]
+    pub article_cache_ttl_hours: i32,
+    /// Query parameters removed from article and image links; `utm_*` style
+    /// entries match by prefix.
+    #[serde(default = "default_tracking_params")]
+    pub tracking_params: Vec<String>,
+    /// Replace links in article bodies with numbered endnotes.
+    #[serde(default)]
+    pub link_endnotes: bool,
+    #[serde(default)]
+    pub html_profile: HtmlProfile,
+}
+
+fn default_connect_timeout() -> i32 {
+    10
+}
+
+fn default_max_response_mb() -> i32 {
+    20
+}
+
+fn default_max_redirects() -> i32 {
+    10
+}
+
+fn default_max_retries() -> i32 {
+    2
+}
+
+fn default_article_cache_ttl_hours() -> i32 {
+    24
+}
+
+pub fn default_tracking_params() -> Vec<String> {
+    crate::util::urls::DEFAULT_TRACKING_PARAMS.iter().map(|p| p.to_string()).collect()
+}
+
+fn default_true() -> bool {
+    true
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
@@ -290,6 +691,33 @@ impl CoverTextSize {
     }
 }
 
+/// Which markup the sanitizer keeps in article bodies.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum HtmlProfile {
+    /// Text, headings, lists, links and images only.
+    Basic,
+    /// Also tables, code, figures, definition lists, sub/superscripts and MathML.
+    #[default]
+    Extended,
+}
+
+impl HtmlProfile {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Basic => "basic",
+            Self::Extended => "extended",
+        }
+    }
+
+    pub fn from_db(value: &str) -> Self {
+        match value {
+            "basic" => Self::Basic,
+            _ => Self::Extended,
+        }
+    }
+}
+
 fn default_cover_text_color() -> CoverTextColor {
     CoverTextColor::default()
 }
@@ -313,6 +741,10 @@ pub enum ProcessorType {
     DomSmoothie = 2,
     Custom = 3,
     TextOnly = 4,
+    /// Use the entry's own content/summary from the feed; the page is never fetched.
+    FeedContent = 5,
+    /// Use the feed's content when it has at least `min_words` words, otherwise scrape.
+    FeedContentOrScrape = 6,
 }
 
 impl Default for ProcessorType {
@@ -327,6 +759,8 @@ impl ProcessorType {
             2 => ProcessorType::DomSmoothie,
             3 => ProcessorType::Custom,
             4 => ProcessorType::TextOnly,
+            5 => ProcessorType::FeedContent,
+            6 => ProcessorType::FeedContentOrScrape,
             _ => ProcessorType::Default,
         }
     }
@@ -341,6 +775,27 @@ pub struct ContentProcessor {
     pub id: Option<i64>,
     pub processor: ProcessorType,
     pub custom_config: Option<String>,
+    /// Tried in order when `processor`'s result looks like a teaser or banner
+    /// rather than the article; `custom_config` applies to a `Custom` step.
+    #[serde(default)]
+    pub fallback_processors: Vec<ProcessorType>,
+}
+
+/// YAML `custom_config` for `ProcessorType::FeedContentOrScrape`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FeedContentConfig {
+    #[serde(default = "default_min_words")]
+    pub min_words: usize,
+}
+
+impl Default for FeedContentConfig {
+    fn default() -> Self {
+        Self { min_words: default_min_words() }
+    }
+}
+
+fn default_min_words() -> usize {
+    150
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
@@ -364,6 +819,60 @@ pub struct CustomExtractorConfig {
     pub discard: Vec<String>,
     #[serde(default)]
     pub output_mode: OutputMode,
+    /// Element holding the article title; `<title>` is used without one.
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub author: Option<String>,
+    #[serde(default)]
+    pub published: Option<String>,
+    /// Image shown above the content, e.g. `meta[property='og:image']`.
+    #[serde(default)]
+    pub lead_image: Option<String>,
+    /// Applied in order after `discard`, before the content is selected.
+    #[serde(default)]
+    pub rewrites: Vec<AttributeRewrite>,
+    /// Use only the first `selector` entry that matches, instead of all of them.
+    #[serde(default)]
+    pub first_match: bool,
+}
+
+/// Copies `from` to `to` and/or removes attributes on the elements matching `selector`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AttributeRewrite {
+    #[serde(default = "default_rewrite_selector")]
+    pub selector: String,
+    #[serde(default)]
+    pub from: Option<String>,
+    #[serde(default)]
+    pub to: Option<String>,
+    #[serde(default)]
+    pub remove: Vec<String>,
+}
+
+fn default_rewrite_selector() -> String {
+    "*".to_string()
+}
+
+/// Selectors used to turn a listing page into feed entries. All selectors but
+/// `item` are evaluated inside each item.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HtmlScrapeConfig {
+    pub item: String,
+    pub title: String,
+    /// Element whose `href` is the entry link; defaults to the first link in the item.
+    #[serde(default)]
+    pub link: Option<String>,
+    #[serde(default)]
+    pub date: Option<String>,
+    /// Read the date from this attribute (e.g. `datetime`) instead of the element text.
+    #[serde(default)]
+    pub date_attribute: Option<String>,
+    /// chrono format string; RFC 3339 and RFC 2822 dates are recognised without one.
+    #[serde(default)]
+    pub date_format: Option<String>,
+    #[serde(default)]
+    pub summary: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -372,7 +881,18 @@ pub struct DomainOverride {
     pub domain: String,
     pub processor: ProcessorType,
     pub custom_config: Option<String>,
+    #[serde(default)]
+    pub fallback_processors: Vec<ProcessorType>,
     pub created_at: String,
+    /// URL templates tried in order when the article cannot be fetched or is too short.
+    #[serde(default)]
+    pub fallback_urls: Vec<String>,
+    /// Extracted articles with fewer words count as failed (0 = only errors do).
+    #[serde(default)]
+    pub min_content_words: usize,
+    /// CSS selector of the link to an article's next page.
+    #[serde(default)]
+    pub next_page_selector: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -380,4 +900,86 @@ pub struct AddDomainOverrideRequest {
     pub domain: String,
     pub processor: ProcessorType,
     pub custom_config: Option<String>,
+    #[serde(default)]
+    pub fallback_processors: Vec<ProcessorType>,
+    #[serde(default)]
+    pub fallback_urls: Vec<String>,
+    #[serde(default)]
+    pub min_content_words: usize,
+    #[serde(default)]
+    pub next_page_selector: Option<String>,
+}
+
+/// Purges the cached extractions of `url`, or the whole article cache without one.
+#[derive(Deserialize)]
+pub struct ArticleCachePurgeQuery {
+    #[serde(default)]
+    pub url: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ArticleCachePurge {
+    pub removed: usize,
+}
+
+#[derive(Deserialize)]
+pub struct DomainOverrideMatchQuery {
+    pub url: String,
+}
+
+/// Existing overrides are replaced by imported rules only with `overwrite`.
+#[derive(Deserialize)]
+pub struct FtrImportQuery {
+    #[serde(default)]
+    pub overwrite: bool,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct FtrImportReport {
+    /// Domain patterns that got an override.
+    pub imported: Vec<String>,
+    pub skipped: Vec<FtrSkippedFile>,
+    pub unconverted: Vec<FtrUnconvertedRule>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct FtrSkippedFile {
+    pub file: String,
+    pub reason: String,
+}
+
+/// A rule that was left out of an imported override.
+#[derive(Serialize, Debug)]
+pub struct FtrUnconvertedRule {
+    pub file: String,
+    pub line: usize,
+    pub rule: String,
+    pub reason: String,
+}
+
+/// Article to extract without saving anything. A domain override draft wins
+/// over `processor`, which wins over the saved override for the URL's domain.
+#[derive(Deserialize)]
+pub struct ExtractionPreviewRequest {
+    pub url: String,
+    #[serde(default)]
+    pub processor: Option<ContentProcessor>,
+    #[serde(default)]
+    pub domain_override: Option<AddDomainOverrideRequest>,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct ExtractionPreview {
+    pub title: String,
+    /// URL the content was read from, which differs from the request after a fallback.
+    pub source_url: Option<String>,
+    /// The article body as it would go into the EPUB.
+    pub html: String,
+    pub author: Option<String>,
+    pub published: Option<String>,
+    pub lead_image: Option<String>,
+    pub word_count: usize,
+    pub pages: usize,
+    pub images: Vec<String>,
+    pub errors: Vec<String>,
 }
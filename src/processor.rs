@@ -1,55 +1,128 @@
-use crate::epub_gen::CoverTextConfig;
-use crate::models::{Feed, GeneralConfig, ReadItLaterArticle};
-use crate::{epub_gen, feed};
+use crate::epub_gen::{CoverTextConfig, EpubOptions};
+use crate::db::delivery_db;
+use crate::models::{ArticleSelection, Feed, GeneralConfig, ReadItLaterArticle};
+use crate::{dedup, epub_gen, feed};
 use anyhow::Result;
 use chrono::{DateTime, Duration as ChronoDuration, Utc};
-use reqwest::Client;
 use rusqlite::Connection;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
 use tracing::{info, warn};
-use crate::feed::{Article, ArticleSource};
-use crate::util::content_extractors;
+use crate::feed::{Article, ArticleSource, EntrySelection};
+use crate::util::{content_extractors, http, urls};
+use crate::util::article_cache::ArticleCache;
+use crate::util::http::HttpClient;
 
 pub const READ_IT_LATER_COVER_TEXT_CONTEXT: &str = "Read it later";
 
+/// Delivered-article ledger entries older than this are pruned.
+const DELIVERED_ARTICLE_RETENTION_DAYS: i64 = 365;
+
+/// The schedule a digest is generated for. For `ArticleSelection::Undelivered`
+/// its delivered articles are used to pick entries.
+#[derive(Debug, Clone, Copy)]
+pub struct ScheduleDelivery {
+    pub schedule_id: i64,
+    pub article_selection: ArticleSelection,
+}
+
+/// Writes a digest of `feeds` to `output_path` and returns the `(entry_id, link)`
+/// ledger keys of its articles, to be recorded with [`record_deliveries`] once
+/// the digest has actually been delivered.
 pub async fn generate_epub(
     feeds: Vec<Feed>,
     _db: &Arc<Mutex<Connection>>,
     output_path: &str,
     fetch_since_hours_override: Option<i32>,
     cover_text_context: Option<String>,
-) -> Result<()> {
+    delivery: Option<ScheduleDelivery>,
+) -> Result<Vec<(String, String)>> {
     info!("Fetching {} feeds...", feeds.len());
 
-    let (fetched_feeds, errors) = feed::fetch_feeds(&feeds).await;
+    // One client for the whole run, so every request shares its connections
+    // and cookies set during the run.
+    let client = http::client().with_cookie_jar();
+    let (fetched_feeds, errors) = feed::fetch_feeds(&feeds, &client, _db).await;
 
-    let (since, image_timeout, cover_text) = {
+    let (selection, epub_options, dedup, cache) = {
         let conn = _db.lock().map_err(|_| anyhow::anyhow!("DB lock failed"))?;
         let config = crate::db::get_general_config(&conn)?;
+        let epub_options = epub_options_from_general_config(&config, cover_text_context);
         let fetch_since_hours = fetch_since_hours_override.unwrap_or(config.fetch_since_hours);
-        let cover_text = cover_text_config_from_general_config(&config, cover_text_context);
-        (Utc::now() - ChronoDuration::hours(fetch_since_hours as i64), config.image_timeout_seconds, cover_text)
+        let since = Utc::now() - ChronoDuration::hours(fetch_since_hours as i64);
+        let selection = match delivery {
+            Some(delivery) if delivery.article_selection == ArticleSelection::Undelivered => {
+                let delivered = delivery_db::get_delivered_articles(&conn, delivery.schedule_id)?;
+                // Entries from before the last delivery's fetch window are never picked,
+                // so old entries of the schedule's feeds (or of feeds added to it later)
+                // aren't delivered in bulk. A schedule that delivered nothing yet starts
+                // with the window before now.
+                let since = delivery_db::last_delivered_at(&conn, delivery.schedule_id)?
+                    .map_or(since, |last| last - ChronoDuration::hours(fetch_since_hours as i64));
+                info!(
+                    "Filtering items since {} not yet delivered by schedule {} ({} delivered)",
+                    since,
+                    delivery.schedule_id,
+                    delivered.entry_ids.len()
+                );
+                EntrySelection::Undelivered { delivered, since }
+            }
+            _ => {
+                info!("Filtering items since: {}", since);
+                EntrySelection::Since(since)
+            }
+        };
+        let dedup = config.dedup_articles.then_some(config.dedup_title_similarity);
+        let cache = ArticleCache::new(_db.clone(), &config);
+        (selection, epub_options, dedup, cache)
     };
-    info!("Filtering items since: {}", since);
-    let articles = feed::filter_items(fetched_feeds, errors, since).await;
+    if let Some(cache) = &cache {
+        cache.prune();
+    }
+    let undelivered_only = matches!(selection, EntrySelection::Undelivered { .. });
+    let mut articles = feed::filter_items(fetched_feeds, errors, selection, &client, cache.as_ref()).await;
 
     if articles.is_empty() {
+        if undelivered_only {
+            return Err(anyhow::anyhow!("No undelivered articles found."));
+        }
         return Err(anyhow::anyhow!("No articles found in the last 24 hours."));
     }
 
-    generate_epub_from_articles(output_path, &articles, image_timeout, cover_text).await?;
+    // Duplicates count as delivered too, so collect the ledger keys before dropping them.
+    let delivered: Vec<(String, String)> = articles
+        .iter()
+        .filter(|a| !a.entry_id.is_empty())
+        .map(|a| (a.entry_id.clone(), a.link.clone()))
+        .collect();
+    if let Some(title_similarity) = dedup {
+        articles = dedup::dedup_articles(articles, title_similarity);
+    }
 
-    Ok(())
+    generate_epub_from_articles(output_path, &articles, epub_options).await?;
+    Ok(delivered)
+}
+
+pub fn record_deliveries(db: &Arc<Mutex<Connection>>, schedule_id: i64, delivered: &[(String, String)]) {
+    let Ok(conn) = db.lock() else {
+        warn!("DB lock failed; delivered articles for schedule {} not recorded", schedule_id);
+        return;
+    };
+    if let Err(e) = delivery_db::record_delivered_articles(&conn, schedule_id, delivered) {
+        warn!("Failed to record delivered articles for schedule {}: {}", schedule_id, e);
+    }
+    let cutoff = Utc::now() - ChronoDuration::days(DELIVERED_ARTICLE_RETENTION_DAYS);
+    if let Err(e) = delivery_db::prune_delivered_articles(&conn, cutoff) {
+        warn!("Failed to prune delivered articles: {}", e);
+    }
 }
 
-async fn generate_epub_from_articles(output_path: &str, articles: &Vec<Article>, image_timeout: i32, cover_text: CoverTextConfig) -> Result<()> {
+async fn generate_epub_from_articles(output_path: &str, articles: &Vec<Article>, options: EpubOptions) -> Result<()> {
     let temp_path = get_temp_file_path(output_path);
     info!("Generating EPUB to temporary file: {:?}", temp_path);
     let file = std::fs::File::create(&temp_path)?;
 
-    match epub_gen::generate_epub_data(&articles, file, image_timeout, cover_text).await {
+    match epub_gen::generate_epub_data(&articles, file, options).await {
         Ok(_) => {
             info!("EPUB generation successful. moving to {}", output_path);
             std::fs::rename(&temp_path, output_path)?;
@@ -85,12 +158,13 @@ pub async fn generate_and_save(
     output_dir: &str,
     fetch_since_hours_override: Option<i32>,
     cover_text_context: Option<String>,
-) -> Result<String> {
+    delivery: Option<ScheduleDelivery>,
+) -> Result<(String, Vec<(String, String)>)> {
     let filename = format!("rss_digest_{}.epub", Utc::now().format("%Y%m%d_%H%M%S"));
     let filepath = format!("{}/{}", output_dir, filename);
 
-    generate_epub(feeds, db, &filepath, fetch_since_hours_override, cover_text_context).await?;
-    Ok(filename)
+    let delivered = generate_epub(feeds, db, &filepath, fetch_since_hours_override, cover_text_context, delivery).await?;
+    Ok((filename, delivered))
 }
 
 pub fn cover_text_config_from_general_config(
@@ -106,11 +180,20 @@ pub fn cover_text_config_from_general_config(
     }
 }
 
+pub fn epub_options_from_general_config(config: &GeneralConfig, cover_text_context: Option<String>) -> EpubOptions {
+    EpubOptions {
+        image_timeout_seconds: config.image_timeout_seconds,
+        cover_text: cover_text_config_from_general_config(config, cover_text_context),
+        link_endnotes: config.link_endnotes,
+        html_profile: config.html_profile,
+    }
+}
+
 pub async fn generate_read_it_later_epub(
     articles: Vec<ReadItLaterArticle>,
     output_dir: &str,
-    image_timeout: i32,
-    cover_text: CoverTextConfig,
+    epub_options: EpubOptions,
+    cache: Option<ArticleCache>,
 ) -> Result<String> {
     let filename = format!(
         "read_it_later_{}.epub",
@@ -120,38 +203,51 @@ pub async fn generate_read_it_later_epub(
 
     info!("Fetching content for {} Read It Later articles...", articles.len());
 
-    let client = Client::builder()
-        .user_agent("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")
-        .timeout(Duration::from_secs(45))
-        .cookie_store(true)
-        .build()
-        .unwrap_or_else(|_| Client::new());
+    let client = http::client().with_cookie_jar();
 
     let mut fetched_articles = Vec::new();
 
-    fetch_all_article_with_content(articles, &client, &mut fetched_articles).await;
+    if let Some(cache) = &cache {
+        cache.prune();
+    }
+    fetch_all_article_with_content(articles, &client, cache.as_ref(), &mut fetched_articles).await;
 
     if fetched_articles.is_empty() {
         return Err(anyhow::anyhow!("No content could be fetched."));
     }
-    generate_epub_from_articles(&filepath, &fetched_articles, image_timeout, cover_text).await?;
+    generate_epub_from_articles(&filepath, &fetched_articles, epub_options).await?;
     Ok(filename)
 }
 
-async fn fetch_all_article_with_content(articles: Vec<ReadItLaterArticle>, client: &Client, fetched_articles: &mut Vec<Article>) {
+async fn fetch_all_article_with_content(
+    articles: Vec<ReadItLaterArticle>,
+    client: &HttpClient,
+    cache: Option<&ArticleCache>,
+    fetched_articles: &mut Vec<Article>,
+) {
     for article in articles {
         info!("Fetching: {}", article.url);
-        match content_extractors::fetch_full_content(&client, &article.url).await {
-            Ok((title, content)) => {
+        match content_extractors::fetch_full_content(&client, &article.url, cache).await {
+            Ok(fetched) => {
                 let article_source=ArticleSource { source: "Read It Later".to_string(), position: 0, category: None };
+                let link = fetched
+                    .metadata
+                    .canonical_url
+                    .filter(|_| fetched.source_url == article.url)
+                    .unwrap_or_else(|| urls::clean_url(&article.url));
                 fetched_articles.push(crate::feed::Article {
-                    title,
-                    link: article.url.clone(),
-                    content,
-                    pub_date: DateTime::parse_from_rfc3339(&article.created_at)
-                        .map(|dt| dt.with_timezone(&Utc))
-                        .unwrap_or_else(|_| Utc::now()),
+                    title: fetched.title,
+                    link,
+                    content: fetched.content,
+                    fetched_from: (fetched.source_url != article.url).then_some(fetched.source_url),
+                    author: fetched.metadata.author,
+                    pub_date: fetched.metadata.published.unwrap_or_else(|| {
+                        DateTime::parse_from_rfc3339(&article.created_at)
+                            .map(|dt| dt.with_timezone(&Utc))
+                            .unwrap_or_else(|_| Utc::now())
+                    }),
                     article_source,
+                    ..Default::default()
                 });
             }
             Err(e) => {
@@ -163,6 +259,7 @@ async fn fetch_all_article_with_content(articles: Vec<ReadItLaterArticle>, clien
                     content: format!("<p>Failed to fetch content: {}</p>", e),
                     pub_date: Utc::now(),
                     article_source,
+                    ..Default::default()
                 });
             }
         }
@@ -1,15 +1,16 @@
 use crate::handlers;
 use crate::models::AppState;
 use axum::{
+    extract::DefaultBodyLimit,
     http::{header, StatusCode},
-    routing::{delete, get, post},
+    routing::{delete, get, post, put},
     Router,
 };
 use std::sync::Arc;
 use tower::ServiceBuilder;
 use tower_http::{services::ServeDir, set_header::SetResponseHeaderLayer};
 use tracing::{info, warn};
-use crate::handlers::{auth_handler, config_handler, domain_override_handler, download_handler, email_handler, feed_handler, read_it_later_handler, schedule_handler};
+use crate::handlers::{article_cache_handler, auth_handler, config_handler, domain_override_handler, download_handler, email_handler, feed_handler, filter_rule_handler, preview_handler, read_it_later_handler, schedule_handler};
 pub const RPUB_USERNAME: &'static str = "RPUB_USERNAME";
 pub const RPUB_PASSWORD: &'static str = "RPUB_PASSWORD";
 
@@ -45,7 +46,19 @@ pub fn create_router(state: Arc<AppState>) -> Router {
         .route("/feeds", get(feed_handler::list_feeds).post(feed_handler::add_feed))
         .route("/feeds/import", post(feed_handler::import_opml))
         .route("/feeds/reorder", post(feed_handler::reorder_feeds))
+        .route("/feeds/health", get(feed_handler::list_feed_health))
         .route("/feeds/{id}", delete(feed_handler::delete_feed).put(feed_handler::update_feed))
+        .route("/feeds/{id}/health", get(feed_handler::get_feed_health))
+        .route("/feeds/{id}/resume", post(feed_handler::resume_feed))
+        .route(
+            "/feeds/{id}/request-options",
+            put(feed_handler::save_request_options).delete(feed_handler::delete_request_options),
+        )
+        .route(
+            "/filter-rules",
+            get(filter_rule_handler::list_filter_rules).post(filter_rule_handler::add_filter_rule),
+        )
+        .route("/filter-rules/{id}", delete(filter_rule_handler::delete_filter_rule))
         .route(
             "/schedules",
             get(schedule_handler::list_schedules).post(schedule_handler::add_schedule),
@@ -83,7 +96,15 @@ pub fn create_router(state: Arc<AppState>) -> Router {
             "/domain-overrides",
             get(domain_override_handler::list_domain_overrides).post(domain_override_handler::add_domain_override),
         )
+        .route("/domain-overrides/match", get(domain_override_handler::match_domain_override))
+        .route(
+            "/domain-overrides/import/ftr",
+            // A full ftr-site-config checkout is well over the default 2 MB.
+            post(domain_override_handler::import_ftr_site_configs).layer(DefaultBodyLimit::max(64 * 1024 * 1024)),
+        )
         .route("/domain-overrides/{id}", delete(domain_override_handler::delete_domain_override))
+        .route("/extraction/preview", post(preview_handler::preview_extraction))
+        .route("/article-cache", delete(article_cache_handler::purge_article_cache))
         .route("/auth/check", get(|| async { StatusCode::OK }));
 
     let protected_routes =add_auth_to_routes(protected_routes);
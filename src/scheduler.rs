@@ -6,9 +6,10 @@ use std::path::Path;
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use tokio_cron_scheduler::{Job, JobScheduler};
-use tracing::{error, info};
+use tracing::{error, info, warn};
 use crate::db::category_db;
 use crate::db::feed_db;
+use crate::util::article_cache::ArticleCache;
 
 const RSS: &'static str = "rss";
 const READ_IT_LATER: &'static str = "read_it_later";
@@ -40,6 +41,10 @@ pub async fn init_scheduler(db_conn: Arc<Mutex<Connection>>) -> Result<JobSchedu
             let category_ids = schedule.category_ids.clone();
             let override_to_email = schedule.override_to_email.clone();
             let fetch_since_hours_override = schedule.fetch_since_hours_override;
+            let delivery = schedule.id.map(|schedule_id| processor::ScheduleDelivery {
+                schedule_id,
+                article_selection: schedule.article_selection,
+            });
             info!("Adding schedule: {}", schedule.cron_expression);
 
             match Job::new_async(schedule.cron_expression.as_str(), move |_uuid, _l| {
@@ -55,6 +60,7 @@ pub async fn init_scheduler(db_conn: Arc<Mutex<Connection>>) -> Result<JobSchedu
                             category_ids,
                             override_to_email,
                             fetch_since_hours_override,
+                            delivery,
                         ).await
                         {
                             error!("Scheduled generation (RSS) failed: {}", e);
@@ -88,6 +94,7 @@ async fn run_scheduled_generation(
     category_ids: Vec<i64>,
     override_to_email: Option<String>,
     fetch_since_hours_override: Option<i32>,
+    delivery: Option<processor::ScheduleDelivery>,
 ) -> Result<()> {
     let (feeds, cover_text_context) = {
         let conn = db.lock().map_err(|_| anyhow::anyhow!("DB lock failed"))?;
@@ -117,15 +124,24 @@ async fn run_scheduled_generation(
         return Ok(());
     }
 
-    let filename = processor::generate_and_save(
+    let (filename, delivered) = processor::generate_and_save(
         feeds,
         &db,
         crate::util::EPUB_OUTPUT_DIR,
         fetch_since_hours_override,
         Some(cover_text_context),
+        delivery,
     ).await?;
     info!("Scheduled generation completed: {}", filename);
-    email::check_and_send_email(db, &filename, override_to_email.as_deref()).await?;
+    let sent = email::check_and_send_email(db.clone(), &filename, override_to_email.as_deref()).await?;
+
+    // Articles only count as delivered once the digest got out, so a failed
+    // send leaves them for the next run.
+    match delivery {
+        Some(delivery) if sent => processor::record_deliveries(&db, delivery.schedule_id, &delivered),
+        Some(delivery) => warn!("Schedule {} digest was not sent; its articles stay undelivered", delivery.schedule_id),
+        None => {}
+    }
 
     Ok(())
 }
@@ -134,16 +150,17 @@ async fn run_read_it_later_generation(
     db: Arc<Mutex<Connection>>,
     override_to_email: Option<String>,
 ) -> Result<()> {
-    let (articles, image_timeout, cover_text) = {
+    let (articles, epub_options, cache) = {
         let conn = db.lock().map_err(|_| anyhow::anyhow!("DB lock failed"))?;
         let articles = db::get_read_it_later_articles(&conn, true)?;
         let config = db::get_general_config(&conn)?;
-        let cover_text = processor::cover_text_config_from_general_config(
+        let epub_options = processor::epub_options_from_general_config(
             &config,
             Some(processor::READ_IT_LATER_COVER_TEXT_CONTEXT.to_string()),
         );
 
-        (articles, config.image_timeout_seconds, cover_text)
+        let cache = ArticleCache::new(db.clone(), &config);
+        (articles, epub_options, cache)
     };
 
     if articles.is_empty() {
@@ -155,8 +172,8 @@ async fn run_read_it_later_generation(
     let filename = processor::generate_read_it_later_epub(
         articles,
         crate::util::EPUB_OUTPUT_DIR,
-        image_timeout,
-        cover_text,
+        epub_options,
+        cache,
     )
     .await?;
     info!("Read It Later generation completed: {}", filename);
@@ -53,9 +53,14 @@ pub struct CoverTemplate<'a> {
 pub struct ArticleTemplate<'a> {
     pub title: &'a str,
     pub source: &'a str,
+    pub author: Option<&'a str>,
+    pub also_in: &'a [String],
     pub pub_date: String,
     pub content: &'a str,
     pub original_link: &'a str,
+    pub fetched_from: Option<&'a str>,
+    /// URLs of the endnotes that `noteref` links in `content` point at.
+    pub link_notes: &'a [String],
     pub back_link: String,
     pub prev_link: Option<String>,
     pub next_link: Option<String>,
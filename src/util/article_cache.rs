@@ -0,0 +1,95 @@
+use std::sync::{Arc, Mutex};
+use chrono::{Duration, Utc};
+use ring::digest::{digest, SHA256};
+use rusqlite::Connection;
+use tracing::warn;
+use crate::db::article_cache_db;
+use crate::models::{CachedArticle, ContentProcessor, GeneralConfig};
+use crate::util::content_extractors::{ArticleMetadata, DomainFetchOptions, FetchedContent};
+
+/// Extracted articles kept in SQLite, so repeated or overlapping runs do not
+/// fetch and extract the same article again. Entries are keyed by URL and a
+/// hash of the extraction settings, and expire after the configured TTL.
+#[derive(Clone)]
+pub struct ArticleCache {
+    db: Arc<Mutex<Connection>>,
+    ttl: Duration,
+}
+
+impl ArticleCache {
+    /// `None` when `article_cache_ttl_hours` disables the cache.
+    pub fn new(db: Arc<Mutex<Connection>>, config: &GeneralConfig) -> Option<Self> {
+        (config.article_cache_ttl_hours > 0).then(|| Self {
+            db,
+            ttl: Duration::hours(config.article_cache_ttl_hours as i64),
+        })
+    }
+
+    pub fn get(&self, url: &str, config_hash: &str) -> Option<FetchedContent> {
+        let conn = self.db.lock().ok()?;
+        let cached = article_cache_db::get_cached_article(&conn, url, config_hash, Utc::now() - self.ttl)
+            .unwrap_or_else(|e| {
+                warn!("Failed to read article cache for {}: {}", url, e);
+                None
+            })?;
+        Some(FetchedContent {
+            title: cached.title,
+            content: cached.content,
+            source_url: cached.source_url,
+            pages: cached.pages,
+            warnings: Vec::new(),
+            metadata: ArticleMetadata {
+                author: cached.author,
+                published: cached.published,
+                lead_image: cached.lead_image,
+                canonical_url: cached.canonical_url,
+            },
+        })
+    }
+
+    pub fn put(&self, url: &str, config_hash: &str, fetched: &FetchedContent) {
+        let article = CachedArticle {
+            url: url.to_string(),
+            title: fetched.title.clone(),
+            content: fetched.content.clone(),
+            source_url: fetched.source_url.clone(),
+            pages: fetched.pages,
+            author: fetched.metadata.author.clone(),
+            published: fetched.metadata.published,
+            lead_image: fetched.metadata.lead_image.clone(),
+            canonical_url: fetched.metadata.canonical_url.clone(),
+        };
+        if let Ok(conn) = self.db.lock()
+            && let Err(e) = article_cache_db::save_cached_article(&conn, config_hash, &article)
+        {
+            warn!("Failed to cache article {}: {}", url, e);
+        }
+    }
+
+    /// Drops expired entries.
+    pub fn prune(&self) {
+        if let Ok(conn) = self.db.lock()
+            && let Err(e) = article_cache_db::prune_article_cache(&conn, Utc::now() - self.ttl)
+        {
+            warn!("Failed to prune article cache: {}", e);
+        }
+    }
+}
+
+/// Identifies the settings an article was extracted with; changing the
+/// processor or its fetch options misses earlier cache entries.
+pub fn config_hash(processor: Option<&ContentProcessor>, options: &DomainFetchOptions) -> String {
+    let key = serde_json::json!({
+        "processor": processor.map(|p| &p.processor),
+        "custom_config": processor.and_then(|p| p.custom_config.as_deref()),
+        "fallback_processors": processor.map(|p| &p.fallback_processors),
+        "fallback_urls": options.fallback_urls,
+        "min_content_words": options.min_content_words,
+        "next_page_selector": options.next_page_selector,
+    });
+    digest(&SHA256, key.to_string().as_bytes())
+        .as_ref()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
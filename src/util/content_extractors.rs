@@ -1,32 +1,227 @@
-use crate::models::{CustomExtractorConfig, ContentProcessor, OutputMode, ProcessorType};
+use crate::util::article_cache::{self, ArticleCache};
+use crate::util::http::HttpClient;
+use crate::util::urls;
+use crate::models::{AttributeRewrite, CustomExtractorConfig, ContentProcessor, FeedContentConfig, OutputMode, ProcessorType};
 use arc_swap::ArcSwap;
-use dom_query::Document;
+use chrono::{DateTime, Utc};
+use dom_query::{Document, Matcher, NodeRef};
 use dom_smoothie::{CandidateSelectMode, Config, TextMode};
-use reqwest::Client;
-use std::collections::HashMap;
+use reqwest::header::HeaderMap;
+use std::collections::HashSet;
 use std::sync::{Arc, OnceLock};
+use tracing::{debug, info, warn};
+use url::Url;
 
-static DOMAIN_OVERRIDES: OnceLock<ArcSwap<HashMap<String, Arc<ContentProcessor>>>> = OnceLock::new();
+/// Domain overrides, most specific pattern first.
+static DOMAIN_OVERRIDES: OnceLock<ArcSwap<Vec<DomainRule>>> = OnceLock::new();
 
-pub fn refresh_domain_overrides(overrides: Vec<(String, ContentProcessor)>) {
-    let map: HashMap<String, Arc<ContentProcessor>> = overrides
+/// Upper bound on pages joined into one article.
+const MAX_ARTICLE_PAGES: usize = 10;
+
+/// What an extraction needs to be taken as the article by a processor chain.
+const MIN_QUALITY_WORDS: usize = 150;
+const MAX_LINK_DENSITY: f64 = 0.5;
+const MIN_QUALITY_PARAGRAPHS: usize = 3;
+
+struct DomainRule {
+    pattern: DomainPattern,
+    processor: Arc<ContentProcessor>,
+    fetch: Arc<DomainFetchOptions>,
+}
+
+/// How a domain's articles are fetched, besides the processor that extracts them.
+#[derive(Debug, Clone, Default)]
+pub struct DomainFetchOptions {
+    /// Templates expanded by [`expand_fallback_url`], tried when the article itself fails.
+    pub fallback_urls: Vec<String>,
+    /// Extracted content with fewer words counts as a failure (0 = only errors do).
+    pub min_content_words: usize,
+    /// Link to the next page of an article; `rel="next"` links are followed without one.
+    pub next_page_selector: Option<String>,
+}
+
+impl DomainFetchOptions {
+    fn is_too_short(&self, content: &str) -> bool {
+        self.min_content_words > 0 && word_count(content) < self.min_content_words
+    }
+}
+
+/// Extracted article together with the URL it was finally read from.
+#[derive(Debug, Clone)]
+pub struct FetchedContent {
+    pub title: String,
+    pub content: String,
+    pub source_url: String,
+    /// Pages joined into `content`.
+    pub pages: usize,
+    /// Problems that did not stop the extraction, such as failed fallbacks.
+    pub warnings: Vec<String>,
+    pub metadata: ArticleMetadata,
+}
+
+/// Which URLs a domain override applies to: `example.com`, `*.example.com` (the
+/// domain and all its subdomains), either optionally followed by a path prefix
+/// such as `example.com/blog`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DomainPattern {
+    host: String,
+    wildcard: bool,
+    /// Without trailing slash; matches whole path segments only.
+    path_prefix: Option<String>,
+}
+
+impl DomainPattern {
+    /// Parses a pattern as entered by the user; a leading `http(s)://` is ignored.
+    pub fn parse(pattern: &str) -> Option<Self> {
+        let pattern = pattern.trim();
+        let pattern = pattern
+            .strip_prefix("https://")
+            .or_else(|| pattern.strip_prefix("http://"))
+            .unwrap_or(pattern);
+        let (host, path) = match pattern.find('/') {
+            Some(i) => pattern.split_at(i),
+            None => (pattern, ""),
+        };
+        let (wildcard, host) = match host.strip_prefix("*.") {
+            Some(rest) => (true, rest),
+            None => (false, host),
+        };
+        if host.is_empty() || host.contains(['*', ':', '@']) || path.contains(['?', '#', '*']) {
+            return None;
+        }
+        // Let `url` normalize case and internationalized names the way page URLs are.
+        let parsed = Url::parse(&format!("http://{}{}", host, path)).ok()?;
+        let host = parsed.host_str()?.trim_end_matches('.').to_string();
+        let path_prefix = Some(parsed.path().trim_end_matches('/').to_string()).filter(|p| !p.is_empty());
+        Some(Self { host, wildcard, path_prefix })
+    }
+
+    pub fn matches(&self, url: &Url) -> bool {
+        let Some(host) = url.host_str() else {
+            return false;
+        };
+        let host = host.to_lowercase();
+        let host = host.trim_end_matches('.');
+        let host_matches = host == self.host
+            || (self.wildcard && host.strip_suffix(self.host.as_str()).is_some_and(|sub| sub.ends_with('.')));
+        host_matches
+            && self.path_prefix.as_deref().is_none_or(|prefix| {
+                let path = url.path();
+                path == prefix || path.strip_prefix(prefix).is_some_and(|rest| rest.starts_with('/'))
+            })
+    }
+
+    /// Sort key putting more specific patterns first: deeper path prefixes, then
+    /// exact hosts before wildcards, then longer hosts.
+    fn specificity(&self) -> (usize, bool, usize) {
+        let depth = self.path_prefix.as_deref().map_or(0, |p| p.matches('/').count());
+        (depth, !self.wildcard, self.host.split('.').count())
+    }
+}
+
+impl std::fmt::Display for DomainPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.wildcard {
+            f.write_str("*.")?;
+        }
+        f.write_str(&self.host)?;
+        f.write_str(self.path_prefix.as_deref().unwrap_or_default())
+    }
+}
+
+/// Index of the most specific of `patterns` matching `url`; earlier patterns win ties.
+pub fn best_matching_pattern<'a>(patterns: impl IntoIterator<Item = &'a DomainPattern>, url: &str) -> Option<usize> {
+    let url = Url::parse(url).ok()?;
+    patterns
+        .into_iter()
+        .enumerate()
+        .filter(|(_, pattern)| pattern.matches(&url))
+        .min_by_key(|(_, pattern)| std::cmp::Reverse(pattern.specificity()))
+        .map(|(i, _)| i)
+}
+
+pub fn refresh_domain_overrides(overrides: Vec<(String, ContentProcessor, DomainFetchOptions)>) {
+    let mut rules: Vec<DomainRule> = overrides
         .into_iter()
-        .map(|(k, v)| (k, Arc::new(v)))
+        .filter_map(|(domain, processor, fetch)| {
+            let Some(pattern) = DomainPattern::parse(&domain) else {
+                warn!("Ignoring domain override with invalid pattern '{}'", domain);
+                return None;
+            };
+            Some(DomainRule { pattern, processor: Arc::new(processor), fetch: Arc::new(fetch) })
+        })
         .collect();
-    
+    rules.sort_by_key(|rule| std::cmp::Reverse(rule.pattern.specificity()));
+
     match DOMAIN_OVERRIDES.get() {
-        Some(swap) => swap.store(Arc::new(map)),
-        None => { let _ = DOMAIN_OVERRIDES.set(ArcSwap::from_pointee(map)); }
+        Some(swap) => swap.store(Arc::new(rules)),
+        None => { let _ = DOMAIN_OVERRIDES.set(ArcSwap::from_pointee(rules)); }
     }
 }
 
+fn with_domain_rule<T>(url: &str, f: impl FnOnce(&DomainRule) -> T) -> Option<T> {
+    let rules = DOMAIN_OVERRIDES.get()?.load();
+    let index = best_matching_pattern(rules.iter().map(|rule| &rule.pattern), url)?;
+    Some(f(&rules[index]))
+}
+
 pub fn get_domain_override(url: &str) -> Option<Arc<ContentProcessor>> {
-    let domain = extract_domain(url)?;
-    DOMAIN_OVERRIDES.get()?.load().get(&domain).cloned()
+    with_domain_rule(url, |rule| rule.processor.clone())
+}
+
+pub fn get_domain_fetch_options(url: &str) -> Option<Arc<DomainFetchOptions>> {
+    with_domain_rule(url, |rule| rule.fetch.clone())
+}
+
+/// Whether `selector` is valid CSS, for checking user supplied selectors.
+pub fn is_valid_selector(selector: &str) -> bool {
+    Matcher::new(selector).is_ok()
+}
+
+/// Builds an alternate URL for `url` from a template. `{url}`, `{url_encoded}`,
+/// `{origin}`, `{host}`, `{path}` and `{query}` are substituted; a template
+/// starting with `?` adds its parameters to the article URL instead.
+pub fn expand_fallback_url(template: &str, url: &str) -> Option<String> {
+    let mut parsed = url::Url::parse(url).ok()?;
+    parsed.set_fragment(None);
+    let template = template.trim();
+
+    let expanded = if let Some(params) = template.strip_prefix('?') {
+        let query = match parsed.query() {
+            Some(existing) if !existing.is_empty() => format!("{}&{}", existing, params),
+            _ => params.to_string(),
+        };
+        parsed.set_query(Some(&query));
+        parsed.to_string()
+    } else {
+        let encoded: String = url::form_urlencoded::byte_serialize(parsed.as_str().as_bytes()).collect();
+        template
+            .replace("{url_encoded}", &encoded)
+            .replace("{url}", parsed.as_str())
+            .replace("{origin}", &parsed.origin().ascii_serialization())
+            .replace("{host}", parsed.host_str().unwrap_or_default())
+            .replace("{path}", parsed.path())
+            .replace("{query}", parsed.query().unwrap_or_default())
+    };
+    url::Url::parse(&expanded).ok().map(|u| u.to_string())
 }
 
 pub trait ContentExtractor: Send + Sync {
     fn extract(&self, html: &str, url: &str) -> anyhow::Result<(String, String)>;
+
+    /// Details about the article beyond its title; most extractors find none.
+    fn metadata(&self, _html: &str, _url: &str) -> ArticleMetadata {
+        ArticleMetadata::default()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ArticleMetadata {
+    pub author: Option<String>,
+    pub published: Option<DateTime<Utc>>,
+    pub lead_image: Option<String>,
+    /// The page's `rel="canonical"` link, without tracking parameters.
+    pub canonical_url: Option<String>,
 }
 
 pub struct DefaultExtractor;
@@ -104,20 +299,35 @@ impl ContentExtractor for CustomExtractor {
 
         let document = Document::from(html);
 
-        let title = match document.try_select("title") {
-            None => {"Untitled".to_string()},
-            Some(x) => {x.text().to_string()}
-        };
+        let title = self
+            .config
+            .title
+            .as_deref()
+            .and_then(|selector| element_value(&document, selector))
+            .or_else(|| document.try_select("title").map(|x| x.text().to_string()))
+            .unwrap_or_else(|| "Untitled".to_string());
 
 
         let cleaned_html = html.to_string();
         let doc = Document::from(cleaned_html.as_str());
         let discard_selector =&self.config.discard.join(", ");
-        let selector =&self.config.selector.join(", ");
         match doc.try_select(discard_selector) {
             None => {}
             Some(dd) => {dd.remove()}
         };
+        for rewrite in &self.config.rewrites {
+            apply_rewrite(&doc, rewrite);
+        }
+        let selector = &if self.config.first_match {
+            self.config
+                .selector
+                .iter()
+                .find(|s| doc.try_select(s).is_some())
+                .cloned()
+                .unwrap_or_default()
+        } else {
+            self.config.selector.join(", ")
+        };
         let mut selected_content =doc.try_select(selector);
         let mut content=String::new();
 
@@ -136,6 +346,69 @@ impl ContentExtractor for CustomExtractor {
 
         Ok((title, content))
     }
+
+    fn metadata(&self, html: &str, url: &str) -> ArticleMetadata {
+        let document = Document::from(html);
+        ArticleMetadata {
+            author: self.config.author.as_deref().and_then(|s| element_value(&document, s)),
+            published: self
+                .config
+                .published
+                .as_deref()
+                .and_then(|s| element_value(&document, s))
+                .and_then(|text| crate::util::html_feed::parse_date(&text, None)),
+            // Text output has nowhere to show an image.
+            lead_image: (self.config.output_mode == OutputMode::Html)
+                .then(|| self.lead_image(&document, url))
+                .flatten(),
+            canonical_url: None,
+        }
+    }
+}
+
+impl CustomExtractor {
+    fn lead_image(&self, document: &Document, url: &str) -> Option<String> {
+        let src = element_value(document, self.config.lead_image.as_deref()?)?;
+        Url::parse(url).ok()?.join(&src).ok().map(|u| u.to_string())
+    }
+}
+
+/// Value of the first element matching `selector`: the `content` of a `meta`,
+/// the `datetime` of a `time`, the source of an image, otherwise its text.
+fn element_value(document: &Document, selector: &str) -> Option<String> {
+    let node = *document.try_select(selector)?.nodes().first()?;
+    let value = match node.node_name().as_deref() {
+        Some("meta") => node.attr("content").map(|v| v.to_string()),
+        Some("time") => node.attr("datetime").map(|v| v.to_string()).or_else(|| Some(node.text().to_string())),
+        Some("img") => image_source(&node),
+        Some("link") => node.attr("href").map(|v| v.to_string()),
+        _ => Some(node.text().to_string()),
+    }?;
+    let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!value.is_empty()).then_some(value)
+}
+
+fn image_source(node: &NodeRef) -> Option<String> {
+    node.attr("src")
+        .filter(|src| !src.trim().is_empty())
+        .or_else(|| node.attr("data-src"))
+        .map(|v| v.to_string())
+}
+
+fn apply_rewrite(doc: &Document, rewrite: &AttributeRewrite) {
+    let Some(selection) = doc.try_select(&rewrite.selector) else {
+        return;
+    };
+    for node in selection.nodes() {
+        if let (Some(from), Some(to)) = (&rewrite.from, &rewrite.to)
+            && let Some(value) = node.attr(from)
+        {
+            node.set_attr(to, &value);
+        }
+        for name in &rewrite.remove {
+            node.remove_attr(name);
+        }
+    }
 }
 pub fn create_extractor(processor: Option<&ContentProcessor>) -> anyhow::Result<Box<dyn ContentExtractor>> {
     let processor_type = processor.map(|p| p.processor).unwrap_or(ProcessorType::Default);
@@ -149,31 +422,600 @@ pub fn create_extractor(processor: Option<&ContentProcessor>) -> anyhow::Result<
                 .ok_or_else(|| anyhow::anyhow!("Custom processor requires custom_config"))?;
             Ok(Box::new(CustomExtractor::new(custom_config)?))
         }
-        ProcessorType::Default => Ok(Box::new(DefaultExtractor)),
+        // Pages that still need scraping under the feed-content modes use the default extractor.
+        ProcessorType::Default | ProcessorType::FeedContent | ProcessorType::FeedContentOrScrape => {
+            Ok(Box::new(DefaultExtractor))
+        }
     }
 }
 
+/// Whether the processor's custom config has a `title` selector, in which case
+/// the extracted title replaces the one the feed gave.
+pub fn extracts_title(processor: Option<&ContentProcessor>) -> bool {
+    processor
+        .filter(|p| p.processor == ProcessorType::Custom)
+        .and_then(|p| p.custom_config.as_deref())
+        .and_then(|c| serde_yaml::from_str::<CustomExtractorConfig>(c).ok())
+        .is_some_and(|c| c.title.is_some_and(|t| !t.trim().is_empty()))
+}
+
+/// Whether an entry should be built from the feed's own body instead of scraping its link.
+pub fn prefers_feed_content(processor: Option<&ContentProcessor>, feed_body: &str) -> bool {
+    let Some(processor) = processor else {
+        return false;
+    };
+    match processor.processor {
+        ProcessorType::FeedContent => true,
+        ProcessorType::FeedContentOrScrape => {
+            let config = processor
+                .custom_config
+                .as_deref()
+                .filter(|c| !c.trim().is_empty())
+                .and_then(|c| serde_yaml::from_str::<FeedContentConfig>(c).ok())
+                .unwrap_or_default();
+            word_count(feed_body) >= config.min_words
+        }
+        _ => false,
+    }
+}
+
+/// Rough signals telling an article from a teaser, a cookie banner or a list of links.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExtractionQuality {
+    pub words: usize,
+    /// Share of the words that are link text.
+    pub link_density: f64,
+    pub paragraphs: usize,
+}
+
+impl ExtractionQuality {
+    pub fn measure(html: &str) -> Self {
+        let doc = Document::from(html);
+        let text = doc.select("body").text();
+        let words = text.split_whitespace().count();
+        let link_words: usize = doc.select("a").iter().map(|a| a.text().split_whitespace().count()).sum();
+        let link_density = if words == 0 { 0.0 } else { link_words as f64 / words as f64 };
+        let paragraphs = match doc.select("p").iter().filter(|p| !p.text().trim().is_empty()).count() {
+            // Text output has no markup; count its non-empty lines instead.
+            0 => text.lines().filter(|l| !l.trim().is_empty()).count(),
+            n => n,
+        };
+        Self { words, link_density, paragraphs }
+    }
+
+    pub fn passes(&self) -> bool {
+        self.words >= MIN_QUALITY_WORDS
+            && self.link_density <= MAX_LINK_DENSITY
+            && self.paragraphs >= MIN_QUALITY_PARAGRAPHS
+    }
+
+    /// Ranks results when no step of a chain passes.
+    fn score(&self) -> f64 {
+        self.words as f64 * (1.0 - self.link_density)
+    }
+}
+
+impl std::fmt::Display for ExtractionQuality {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} words, link density {:.2}, {} paragraphs",
+            self.words, self.link_density, self.paragraphs
+        )
+    }
+}
+
+pub fn word_count(html: &str) -> usize {
+    Document::from(html).select("body").text().split_whitespace().count()
+}
+
 pub fn extract_domain(url: &str) -> Option<String> {
     url::Url::parse(url)
         .ok()
         .and_then(|u| u.host_str().map(|h| h.to_lowercase()))
 }
 
-pub async fn fetch_full_content(client: &Client, url: &str) -> anyhow::Result<(String, String)> {
-    fetch_full_content_with_processor(client, url, None).await
+pub async fn fetch_full_content(
+    client: &HttpClient,
+    url: &str,
+    cache: Option<&ArticleCache>,
+) -> anyhow::Result<FetchedContent> {
+    fetch_full_content_with_processor(client, url, None, &HeaderMap::new(), None, cache).await
 }
+
+/// Fetches and extracts an article using its domain override, if any. A cached
+/// extraction with the same settings is returned without fetching anything.
+/// `feed_body` is the entry's content from its feed, for `FeedContent` chain steps.
 pub async fn fetch_full_content_with_processor(
-    client: &Client,
+    client: &HttpClient,
     url: &str,
     processor: Option<&ContentProcessor>,
-) -> anyhow::Result<(String, String)> {
-    let html = client.get(url).send().await?.text().await?;
+    headers: &HeaderMap,
+    feed_body: Option<&str>,
+    cache: Option<&ArticleCache>,
+) -> anyhow::Result<FetchedContent> {
+    // A domain override takes precedence over the caller's processor.
+    let domain_processor = get_domain_override(url);
+    let processor = domain_processor.as_deref().or(processor);
+    let options = get_domain_fetch_options(url).unwrap_or_default();
+    // What a feed's credentials fetch may differ from what anyone else gets, so
+    // such fetches neither use nor fill the cache.
+    let cache = cache.filter(|_| headers.is_empty());
 
-    let extractor = if let Some(content_processor) = get_domain_override(url) {
-        create_extractor(Some(&*content_processor))?
-    } else {
-        create_extractor(processor)?
+    let config_hash = cache.map(|_| article_cache::config_hash(processor, &options));
+    if let (Some(cache), Some(hash)) = (cache, &config_hash)
+        && let Some(cached) = cache.get(url, hash)
+    {
+        debug!("Using cached extraction of {}", url);
+        return Ok(cached);
+    }
+    let fetched = fetch_content(client, url, processor, &options, headers, feed_body).await?;
+    // Degraded results (too short, or only reached past errors) are retried next time.
+    let degraded = !fetched.warnings.is_empty() || options.is_too_short(&fetched.content);
+    if let (Some(cache), Some(hash)) = (cache, &config_hash)
+        && !degraded
+    {
+        cache.put(url, hash, &fetched);
+    }
+    Ok(fetched)
+}
+
+/// Fetches and extracts an article with exactly the given processor and fetch
+/// options. A processor with fallback processors runs them as a chain, see
+/// [`extract_with_chain`]. Fallback URLs are tried in order after an error or
+/// too short a result; if none is long enough the longest extraction wins.
+pub async fn fetch_content(
+    client: &HttpClient,
+    url: &str,
+    processor: Option<&ContentProcessor>,
+    options: &DomainFetchOptions,
+    headers: &HeaderMap,
+    feed_body: Option<&str>,
+) -> anyhow::Result<FetchedContent> {
+    let extractor = create_extractor(processor)?;
+    let next_page_selector = options.next_page_selector.as_deref();
+    let primary = match processor.filter(|p| !p.fallback_processors.is_empty()) {
+        Some(chain) => extract_with_chain(client, url, chain, next_page_selector, headers, feed_body).await,
+        None => fetch_and_extract(client, url, &*extractor, next_page_selector, headers).await,
+    };
+    if options.fallback_urls.is_empty() {
+        return primary;
+    }
+
+    let mut warnings = Vec::new();
+    let mut best = None;
+    let primary_error = match primary {
+        Ok(fetched) if !options.is_too_short(&fetched.content) => return Ok(fetched),
+        Ok(fetched) => {
+            note(&mut warnings, format!("Content of {} is shorter than {} words, trying fallbacks", url, options.min_content_words));
+            best = Some(fetched);
+            None
+        }
+        Err(e) => {
+            note(&mut warnings, format!("Failed to fetch {}, trying fallbacks: {}", url, e));
+            Some(e)
+        }
     };
-    
-    extractor.extract(&html, url)
-}
\ No newline at end of file
+
+    let host = extract_domain(url);
+    for template in &options.fallback_urls {
+        let Some(candidate) = expand_fallback_url(template, url) else {
+            note(&mut warnings, format!("Fallback template '{}' does not give a valid URL for {}", template, url));
+            continue;
+        };
+        let candidate_headers = if extract_domain(&candidate) == host { headers.clone() } else { HeaderMap::new() };
+        // Fallbacks are extracted like the original unless they have an override of their own.
+        let own_extractor = match get_domain_override(&candidate) {
+            Some(candidate_processor) => match create_extractor(Some(&candidate_processor)) {
+                Ok(own) => Some(own),
+                Err(e) => {
+                    note(&mut warnings, format!("Override for fallback {} is unusable: {}", candidate, e));
+                    continue;
+                }
+            },
+            None => None,
+        };
+        let candidate_selector = get_domain_fetch_options(&candidate).and_then(|o| o.next_page_selector.clone());
+        let candidate_extractor = own_extractor.as_deref().unwrap_or(&*extractor);
+        match fetch_and_extract(client, &candidate, candidate_extractor, candidate_selector.as_deref(), &candidate_headers).await {
+            Ok(mut fetched) if !options.is_too_short(&fetched.content) => {
+                info!("Using fallback {} for {}", candidate, url);
+                warnings.append(&mut fetched.warnings);
+                fetched.warnings = warnings;
+                return Ok(fetched);
+            }
+            Ok(fetched) => {
+                if best.as_ref().is_none_or(|b: &FetchedContent| word_count(&b.content) < word_count(&fetched.content)) {
+                    best = Some(fetched);
+                }
+            }
+            Err(e) => note(&mut warnings, format!("Fallback {} for {} failed: {}", candidate, url, e)),
+        }
+    }
+
+    match (best, primary_error) {
+        (Some(mut fetched), _) => {
+            warnings.append(&mut fetched.warnings);
+            fetched.warnings = warnings;
+            Ok(fetched)
+        }
+        (None, Some(e)) => Err(e),
+        (None, None) => Err(anyhow::anyhow!("No content could be extracted from {}", url)),
+    }
+}
+
+fn note(warnings: &mut Vec<String>, message: String) {
+    warn!("{}", message);
+    warnings.push(message);
+}
+
+/// Runs `processor` and then each of its fallback processors on the article,
+/// returning the first result that passes [`ExtractionQuality::passes`] or,
+/// failing that, the best scoring one. The page is fetched once for all steps;
+/// `FeedContent` steps use `feed_body` and are skipped without one.
+async fn extract_with_chain(
+    client: &HttpClient,
+    url: &str,
+    processor: &ContentProcessor,
+    next_page_selector: Option<&str>,
+    headers: &HeaderMap,
+    feed_body: Option<&str>,
+) -> anyhow::Result<FetchedContent> {
+    let mut warnings = Vec::new();
+    let mut page = None;
+    let mut best: Option<(ProcessorType, FetchedContent, ExtractionQuality)> = None;
+    let steps = std::iter::once(processor.processor).chain(processor.fallback_processors.iter().copied());
+    for step in steps {
+        let result = match step {
+            ProcessorType::FeedContent | ProcessorType::FeedContentOrScrape => {
+                let Some(body) = feed_body.filter(|b| !b.trim().is_empty()) else {
+                    continue;
+                };
+                Ok(FetchedContent {
+                    title: String::new(),
+                    content: body.to_string(),
+                    source_url: url.to_string(),
+                    pages: 1,
+                    warnings: Vec::new(),
+                    metadata: ArticleMetadata::default(),
+                })
+            }
+            _ => {
+                if page.is_none() {
+                    page = Some(client.get_page(url, headers).await);
+                }
+                let Some(Ok((final_url, html))) = &page else {
+                    continue;
+                };
+                let step_processor = ContentProcessor {
+                    processor: step,
+                    fallback_processors: Vec::new(),
+                    ..processor.clone()
+                };
+                match create_extractor(Some(&step_processor)) {
+                    Ok(extractor) => {
+                        extract_pages(client, url, final_url, html, &*extractor, next_page_selector, headers).await
+                    }
+                    Err(e) => Err(e),
+                }
+            }
+        };
+        match result {
+            Ok(mut fetched) => {
+                let quality = ExtractionQuality::measure(&fetched.content);
+                if quality.passes() {
+                    info!("Extracted {} with {:?} ({})", url, step, quality);
+                    warnings.append(&mut fetched.warnings);
+                    fetched.warnings = warnings;
+                    return Ok(fetched);
+                }
+                note(&mut warnings, format!("{:?} result for {} is below the quality threshold ({})", step, url, quality));
+                if best.as_ref().is_none_or(|(_, _, q)| q.score() < quality.score()) {
+                    best = Some((step, fetched, quality));
+                }
+            }
+            Err(e) => note(&mut warnings, format!("{:?} extraction of {} failed: {}", step, url, e)),
+        }
+    }
+
+    match (best, page) {
+        (Some((step, mut fetched, quality)), _) => {
+            info!("Extracted {} with {:?}, the best of its chain ({})", url, step, quality);
+            warnings.append(&mut fetched.warnings);
+            fetched.warnings = warnings;
+            Ok(fetched)
+        }
+        (None, Some(Err(e))) => Err(e),
+        (None, _) => Err(anyhow::anyhow!("No processor in the chain could extract {}", url)),
+    }
+}
+
+async fn fetch_and_extract(
+    client: &HttpClient,
+    url: &str,
+    extractor: &dyn ContentExtractor,
+    next_page_selector: Option<&str>,
+    headers: &HeaderMap,
+) -> anyhow::Result<FetchedContent> {
+    let (final_url, html) = client.get_page(url, headers).await?;
+    extract_pages(client, url, &final_url, &html, extractor, next_page_selector, headers).await
+}
+
+/// Extracts the first page of `url`, already fetched from `final_url`, and the
+/// pages following it.
+async fn extract_pages(
+    client: &HttpClient,
+    url: &str,
+    final_url: &str,
+    html: &str,
+    extractor: &dyn ContentExtractor,
+    next_page_selector: Option<&str>,
+    headers: &HeaderMap,
+) -> anyhow::Result<FetchedContent> {
+    let (title, mut content) = extractor.extract(html, final_url)?;
+    let mut metadata = extractor.metadata(html, final_url);
+    metadata.canonical_url = urls::canonical_link(html, final_url);
+    if let Some(image) = &metadata.lead_image
+        && !content.contains(image.as_str())
+    {
+        content = format!("<p><img src=\"{}\" alt=\"\" /></p>{}", image, content);
+    }
+    let mut content = resolve_urls(&content, html, final_url);
+
+    // Follow the article onto its further pages, extracting each one the same way.
+    let mut warnings = Vec::new();
+    let mut pages = 1;
+    let mut visited = HashSet::from([without_fragment(url)]);
+    let mut last_page = content.clone();
+    visited.insert(without_fragment(final_url));
+    let mut page_url = final_url.to_string();
+    let mut page_html = html.to_string();
+    while pages < MAX_ARTICLE_PAGES {
+        let Some(next) = next_page_url(&page_html, &page_url, url, next_page_selector) else {
+            break;
+        };
+        if !visited.insert(next.clone()) {
+            debug!("Pagination of {} loops back to {}", url, next);
+            break;
+        }
+        let (next_final, html) = match client.get_page(&next, headers).await {
+            Ok(page) => page,
+            Err(e) => {
+                note(&mut warnings, format!("Failed to fetch page {} of {}: {}", next, url, e));
+                break;
+            }
+        };
+        match extractor.extract(&html, &next_final) {
+            // Sites often answer pages past the end with a page seen before.
+            Ok((_, page)) if page.trim().is_empty() || page == last_page => break,
+            Ok((_, page)) => {
+                content.push('\n');
+                content.push_str(&resolve_urls(&page, &html, &next_final));
+                last_page = page;
+            }
+            Err(e) => {
+                note(&mut warnings, format!("Failed to extract page {} of {}: {}", next, url, e));
+                break;
+            }
+        }
+        visited.insert(without_fragment(&next_final));
+        pages += 1;
+        page_url = next_final;
+        page_html = html;
+    }
+    if pages > 1 {
+        info!("Joined {} pages of {}", pages, url);
+    }
+
+    Ok(FetchedContent { title, content, source_url: url.to_string(), pages, warnings, metadata })
+}
+
+/// Makes the links and images of extracted content absolute, resolved against
+/// the page's `<base href>` or its final URL.
+fn resolve_urls(content: &str, page_html: &str, page_url: &str) -> String {
+    match urls::document_base(page_html, page_url) {
+        Some(base) => urls::absolutize_urls(content, &base),
+        None => content.to_string(),
+    }
+}
+
+/// The next page of a paginated article, if `html` links to one. Without a
+/// selector only `rel="next"` links below the first page's path are followed,
+/// so links to the next post are not mistaken for pagination.
+fn next_page_url(html: &str, page_url: &str, first_url: &str, selector: Option<&str>) -> Option<String> {
+    let doc = Document::from(html);
+    let href = match selector {
+        Some(selector) => doc.try_select(selector)?.first().attr("href"),
+        None => doc.try_select("link[rel~='next'][href], a[rel~='next'][href]")?.first().attr("href"),
+    }?;
+
+    let first = Url::parse(first_url).ok()?;
+    let next = Url::parse(page_url).ok()?.join(href.trim()).ok()?;
+    if next.host_str() != first.host_str() {
+        return None;
+    }
+    // Without a site-specific selector, only follow links below the article
+    // itself, compared by whole path segments as in `DomainPattern::matches`.
+    let prefix = first.path().trim_end_matches('/');
+    let path = next.path();
+    if selector.is_none() && path != prefix && !path.strip_prefix(prefix).is_some_and(|rest| rest.starts_with('/')) {
+        return None;
+    }
+    let next = without_fragment(next.as_str());
+    (next != without_fragment(page_url)).then_some(next)
+}
+
+fn without_fragment(url: &str) -> String {
+    url.split('#').next().unwrap_or(url).to_string()
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn processor(processor: ProcessorType, custom_config: Option<&str>) -> ContentProcessor {
+        ContentProcessor {
+            id: None,
+            processor,
+            custom_config: custom_config.map(str::to_string),
+            fallback_processors: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn most_specific_domain_pattern_wins() {
+        let patterns: Vec<DomainPattern> =
+            ["*.example.com", "example.com", "Example.com/blog/", "*.example.com/blog/2024", "EXAMPLE.com/News"]
+                .iter()
+                .map(|p| DomainPattern::parse(p).unwrap())
+                .collect();
+        assert_eq!(patterns[2].to_string(), "example.com/blog");
+        // Hosts are case-insensitive, paths are not.
+        assert_eq!(patterns[4].to_string(), "example.com/News");
+        let best = |url| best_matching_pattern(&patterns, url);
+        assert_eq!(best("https://example.com/news/a"), Some(1));
+        assert_eq!(best("https://example.com/News/a"), Some(4));
+        assert_eq!(best("https://m.example.com/news/a"), Some(0));
+        assert_eq!(best("https://example.com/blog/a"), Some(2));
+        assert_eq!(best("https://example.com/blogger/a"), Some(1));
+        assert_eq!(best("https://www.example.com/blog/2024/a"), Some(3));
+        assert_eq!(best("https://notexample.com/"), None);
+        assert!(DomainPattern::parse("*.").is_none());
+        assert!(DomainPattern::parse("ex*ample.com").is_none());
+    }
+
+    #[test]
+    fn only_custom_title_selectors_replace_feed_titles() {
+        assert!(extracts_title(Some(&processor(ProcessorType::Custom, Some("selector: [article]\ntitle: h1.headline")))));
+        assert!(!extracts_title(Some(&processor(ProcessorType::Custom, Some("selector: [article]")))));
+        assert!(!extracts_title(Some(&processor(ProcessorType::Default, Some("title: h1")))));
+        assert!(!extracts_title(None));
+    }
+
+    #[test]
+    fn quality_rejects_teasers_and_link_lists() {
+        let sentence = "This sentence is part of a longer article body about something. ";
+        let article = format!("<p>{}</p>", sentence.repeat(8)).repeat(3);
+        assert!(ExtractionQuality::measure(&article).passes());
+
+        let teaser = format!("<p>{}</p><p><a href=\"/more\">Read more</a></p>", sentence.repeat(3));
+        assert!(!ExtractionQuality::measure(&teaser).passes());
+
+        let links = format!("<ul>{}</ul>", format!("<li><a href=\"/x\">{}</a></li>", sentence).repeat(20));
+        let quality = ExtractionQuality::measure(&links);
+        assert!(quality.link_density > MAX_LINK_DENSITY);
+        assert!(!quality.passes());
+    }
+
+    #[test]
+    fn feed_content_modes_choose_feed_body() {
+        let body = "<p>one two three four five</p>";
+        assert!(!prefers_feed_content(None, body));
+        assert!(!prefers_feed_content(Some(&processor(ProcessorType::Default, None)), body));
+        assert!(prefers_feed_content(Some(&processor(ProcessorType::FeedContent, None)), ""));
+
+        let hybrid = processor(ProcessorType::FeedContentOrScrape, Some("min_words: 5"));
+        assert!(prefers_feed_content(Some(&hybrid), body));
+        assert!(!prefers_feed_content(Some(&hybrid), "<p>too short</p>"));
+        assert!(!prefers_feed_content(Some(&processor(ProcessorType::FeedContentOrScrape, None)), body));
+    }
+
+    #[test]
+    fn custom_extractor_reads_metadata_and_rewrites_attributes() {
+        let html = r#"<html><head><title>Site | Story</title>
+            <meta property="og:image" content="/images/lead.jpg"></head><body>
+            <h1 class="headline"> The  Story </h1>
+            <span class="byline">Jane Doe</span>
+            <time datetime="2024-03-05T10:00:00Z">March 5</time>
+            <div class="body"><p style="color:red">Text</p><img data-src="/a.jpg" src=""></div>
+            </body></html>"#;
+        let extractor = CustomExtractor::new(
+            "selector: ['.body']\ntitle: h1.headline\nauthor: .byline\npublished: time\nlead_image: \"meta[property='og:image']\"\nrewrites:\n  - selector: 'img[data-src]'\n    from: data-src\n    to: src\n  - remove: [style]\n",
+        )
+        .unwrap();
+
+        let (title, content) = extractor.extract(html, "https://example.com/story").unwrap();
+        assert_eq!(title, "The Story");
+        assert!(content.contains(r#"src="/a.jpg""#));
+        assert!(!content.contains("style="));
+
+        let metadata = extractor.metadata(html, "https://example.com/story");
+        assert_eq!(metadata.author.as_deref(), Some("Jane Doe"));
+        assert_eq!(metadata.published.unwrap().to_rfc3339(), "2024-03-05T10:00:00+00:00");
+        assert_eq!(metadata.lead_image.as_deref(), Some("https://example.com/images/lead.jpg"));
+
+        let legacy = CustomExtractor::new("selector: ['.body']\ndiscard: ['img']\n").unwrap();
+        let (title, content) = legacy.extract(html, "https://example.com/story").unwrap();
+        assert_eq!(title, "Site | Story");
+        assert!(!content.contains("<img"));
+        assert_eq!(legacy.metadata(html, "https://example.com/story"), ArticleMetadata::default());
+    }
+
+    #[test]
+    fn fallback_templates_expand_against_article_url() {
+        let url = "https://news.example.com/2024/story?id=7#comments";
+        assert_eq!(
+            expand_fallback_url("https://archive.example/newest/{url}", url).as_deref(),
+            Some("https://archive.example/newest/https://news.example.com/2024/story?id=7")
+        );
+        assert_eq!(
+            expand_fallback_url("https://mirror.example/?u={url_encoded}", url).as_deref(),
+            Some("https://mirror.example/?u=https%3A%2F%2Fnews.example.com%2F2024%2Fstory%3Fid%3D7")
+        );
+        assert_eq!(
+            expand_fallback_url("{origin}/amp{path}", url).as_deref(),
+            Some("https://news.example.com/amp/2024/story")
+        );
+        assert_eq!(
+            expand_fallback_url("?output=1", url).as_deref(),
+            Some("https://news.example.com/2024/story?id=7&output=1")
+        );
+        assert_eq!(
+            expand_fallback_url("?print", "https://news.example.com/a").as_deref(),
+            Some("https://news.example.com/a?print")
+        );
+        assert_eq!(expand_fallback_url("{path}", url), None);
+
+        let fallback = DomainFetchOptions { min_content_words: 3, ..Default::default() };
+        assert!(fallback.is_too_short("<p>two words</p>"));
+        assert!(!fallback.is_too_short("<p>now three words</p>"));
+    }
+
+    #[test]
+    fn next_page_links_stay_within_the_article() {
+        let first = "https://news.example.com/2024/story";
+        let page = |links: &str| format!("<html><head>{}</head><body><p>Text</p></body></html>", links);
+
+        let rel_next = page(r#"<link rel="next" href="/2024/story/2/#top">"#);
+        assert_eq!(
+            next_page_url(&rel_next, first, first, None).as_deref(),
+            Some("https://news.example.com/2024/story/2/")
+        );
+        let query = page(r#"<link rel="prev next" href="?page=2">"#);
+        assert_eq!(
+            next_page_url(&query, first, first, None).as_deref(),
+            Some("https://news.example.com/2024/story?page=2")
+        );
+
+        let next_post = page(r#"<a rel="next" href="/2024/another-story">Next post</a>"#);
+        assert_eq!(next_page_url(&next_post, first, first, None), None);
+        let shared_prefix = page(r#"<a rel="next" href="/2024/story-two">Next post</a>"#);
+        assert_eq!(next_page_url(&shared_prefix, first, first, None), None);
+        let home = "https://news.example.com/";
+        assert_eq!(
+            next_page_url(&page(r#"<link rel="next" href="/page/2">"#), home, home, None).as_deref(),
+            Some("https://news.example.com/page/2")
+        );
+        let other_host = page(r#"<link rel="next" href="https://other.example.com/2024/story/2">"#);
+        assert_eq!(next_page_url(&other_host, first, first, None), None);
+        let itself = page(r#"<link rel="next" href="/2024/story#comments">"#);
+        assert_eq!(next_page_url(&itself, first, first, None), None);
+
+        let custom = r#"<nav class="pager"><a href="/read/story-p2">More</a></nav>"#;
+        assert_eq!(
+            next_page_url(custom, first, first, Some(".pager a")).as_deref(),
+            Some("https://news.example.com/read/story-p2")
+        );
+        assert!(is_valid_selector(".pager a"));
+        assert!(!is_valid_selector(".pager >> a"));
+    }
+}
@@ -0,0 +1,180 @@
+//! Reads FiveFilters `ftr-site-config` rule files into custom extractor configs.
+
+use crate::models::{CustomExtractorConfig, FtrUnconvertedRule};
+use crate::util::content_extractors::{is_valid_selector, DomainPattern};
+use crate::util::xpath::xpath_to_css;
+
+/// Directives that only matter to the FiveFilters test suite or HTML tidying.
+const IGNORED_DIRECTIVES: &[&str] = &["test_url", "test_contains", "tidy", "skip_json_ld"];
+
+/// One rule file converted to the domain override it describes.
+#[derive(Debug)]
+pub struct SiteConfig {
+    pub patterns: Vec<String>,
+    pub extractor: CustomExtractorConfig,
+    pub next_page_selector: Option<String>,
+    pub unconverted: Vec<FtrUnconvertedRule>,
+}
+
+/// The domain patterns a rule file applies to, from its name: `example.com.txt`
+/// covers the bare and `www.` host, `.example.com.txt` every subdomain.
+fn file_patterns(file_name: &str) -> Result<Vec<String>, String> {
+    let Some(host) = file_name.strip_suffix(".txt") else {
+        return Err("not a .txt rule file".to_string());
+    };
+    if host == "global" {
+        return Err("site-independent rules are not imported".to_string());
+    }
+    let patterns = match host.strip_prefix('.') {
+        Some(domain) => vec![format!("*.{}", domain)],
+        None if host.starts_with("www.") => vec![host.to_string()],
+        None => vec![host.to_string(), format!("www.{}", host)],
+    };
+    patterns
+        .into_iter()
+        .map(|p| DomainPattern::parse(&p).map(|p| p.to_string()).ok_or_else(|| format!("invalid domain {}", host)))
+        .collect()
+}
+
+/// Element XPath for a metadata rule; `element_value` already reads the
+/// attribute or text these suffixes select.
+fn metadata_xpath(xpath: &str) -> &str {
+    ["//text()", "/text()", "/@content", "/@datetime", "/@src", "/@href"]
+        .iter()
+        .find_map(|suffix| xpath.strip_suffix(suffix))
+        .unwrap_or(xpath)
+}
+
+fn escape_attr(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+pub fn parse_site_config(file_name: &str, text: &str) -> Result<SiteConfig, String> {
+    let patterns = file_patterns(file_name)?;
+    let mut extractor = CustomExtractorConfig {
+        selector: Vec::new(),
+        discard: Vec::new(),
+        output_mode: Default::default(),
+        title: None,
+        author: None,
+        published: None,
+        lead_image: None,
+        rewrites: Vec::new(),
+        first_match: true,
+    };
+    let (mut titles, mut authors, mut dates) = (Vec::new(), Vec::new(), Vec::new());
+    let mut next_page_selector = None;
+    let mut unconverted = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut reject = |reason: String| {
+            unconverted.push(FtrUnconvertedRule {
+                file: file_name.to_string(),
+                line: index + 1,
+                rule: line.to_string(),
+                reason,
+            })
+        };
+        let Some((directive, value)) = line.split_once(':') else {
+            reject("not a directive".to_string());
+            continue;
+        };
+        let (directive, value) = (directive.trim(), value.trim());
+        if IGNORED_DIRECTIVES.contains(&directive)
+            || (matches!(directive, "prune" | "autodetect_on_failure") && value == "no")
+        {
+            continue;
+        }
+        let selector = match directive {
+            "body" | "strip" | "next_page_link" => xpath_to_css(value),
+            "title" | "author" | "date" => xpath_to_css(metadata_xpath(value)),
+            "strip_id_or_class" => {
+                let value = escape_attr(value.trim_matches(['\'', '"']));
+                Ok(format!("[class*=\"{0}\"], [id*=\"{0}\"]", value))
+            }
+            "strip_image_src" => Ok(format!("img[src*=\"{}\"]", escape_attr(value.trim_matches(['\'', '"'])))),
+            _ => {
+                reject("directive is not supported".to_string());
+                continue;
+            }
+        };
+        let selector = match selector {
+            Ok(selector) if is_valid_selector(&selector) => selector,
+            Ok(selector) => {
+                reject(format!("translated selector {} is not supported", selector));
+                continue;
+            }
+            Err(e) => {
+                reject(format!("XPath not converted: {}", e));
+                continue;
+            }
+        };
+        match directive {
+            "body" => extractor.selector.push(selector),
+            "title" => titles.push(selector),
+            "author" => authors.push(selector),
+            "date" => dates.push(selector),
+            "next_page_link" => next_page_selector = next_page_selector.or(Some(selector)),
+            _ => extractor.discard.push(selector),
+        }
+    }
+
+    if extractor.selector.is_empty() {
+        return Err("no usable body rule".to_string());
+    }
+    let join = |selectors: Vec<String>| (!selectors.is_empty()).then(|| selectors.join(", "));
+    extractor.title = join(titles);
+    extractor.author = join(authors);
+    extractor.published = join(dates);
+    Ok(SiteConfig { patterns, extractor, next_page_selector, unconverted })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_rule_file_and_reports_leftovers() {
+        let text = "\
+# Example rules
+title: //meta[@property='og:title']/@content
+author: //span[@class='byline']/text()
+body: //div[@itemprop='articleBody']
+body: //article
+strip: //aside | //div[contains(@class, 'related')]
+strip_id_or_class: newsletter
+strip_image_src: pixel.gif
+next_page_link: //a[@rel='next']
+replace_string(<br><br>): <p>
+single_page_link: //a[contains(., 'Single page')]
+strip: //div/..
+prune: no
+test_url: https://example.com/story
+";
+        let config = parse_site_config("example.com.txt", text).unwrap();
+        assert_eq!(config.patterns, ["example.com", "www.example.com"]);
+        assert_eq!(config.extractor.selector, ["div[itemprop=\"articleBody\"]", "article"]);
+        assert!(config.extractor.first_match);
+        assert_eq!(
+            config.extractor.discard,
+            [
+                "aside, div[class*=\"related\"]",
+                "[class*=\"newsletter\"], [id*=\"newsletter\"]",
+                "img[src*=\"pixel.gif\"]",
+            ]
+        );
+        assert_eq!(config.extractor.title.as_deref(), Some("meta[property=\"og:title\"]"));
+        assert_eq!(config.extractor.author.as_deref(), Some("span[class=\"byline\"]"));
+        assert_eq!(config.next_page_selector.as_deref(), Some("a[rel=\"next\"]"));
+        let lines: Vec<usize> = config.unconverted.iter().map(|r| r.line).collect();
+        assert_eq!(lines, [10, 11, 12]);
+
+        assert_eq!(parse_site_config(".example.org.txt", "body: //main").unwrap().patterns, ["*.example.org"]);
+        assert!(parse_site_config("example.net.txt", "title: //h1").is_err());
+        assert!(parse_site_config("README.md", "body: //main").is_err());
+    }
+}
@@ -0,0 +1,168 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use dom_query::{Document, Selection};
+use feed_rs::model::Feed;
+use feed_rs::parser;
+use serde_json::json;
+use url::Url;
+use crate::models::HtmlScrapeConfig;
+use crate::util::content_extractors::is_valid_selector;
+
+pub fn parse_scrape_config(yaml: &str) -> anyhow::Result<HtmlScrapeConfig> {
+    let config: HtmlScrapeConfig =
+        serde_yaml::from_str(yaml).map_err(|e| anyhow::anyhow!("Invalid scrape config: {}", e))?;
+    if config.item.trim().is_empty() || config.title.trim().is_empty() {
+        anyhow::bail!("Scrape config needs `item` and `title` selectors");
+    }
+    let selectors = [
+        Some(&config.item),
+        Some(&config.title),
+        config.link.as_ref(),
+        config.date.as_ref(),
+        config.summary.as_ref(),
+    ];
+    if let Some(invalid) = selectors.into_iter().flatten().find(|s| !is_valid_selector(s)) {
+        anyhow::bail!("Invalid CSS selector in scrape config: {}", invalid);
+    }
+    Ok(config)
+}
+
+/// Scrapes a listing page into a feed. The entries are assembled as a JSON Feed
+/// document and run through `feed_rs`, so they look exactly like those of a
+/// regular feed. Items without a title or link are skipped, and items without a
+/// usable date are dated at the time of the scrape so the fetch window keeps them.
+pub fn scrape_listing(html: &str, page_url: &str, config: &HtmlScrapeConfig) -> anyhow::Result<Feed> {
+    let base = Url::parse(page_url)?;
+    let fetched_at = Utc::now();
+    let doc = Document::from(html);
+    let page_title = doc.select("title").text().trim().to_string();
+
+    let items_sel = doc
+        .try_select(&config.item)
+        .ok_or_else(|| anyhow::anyhow!("Invalid item selector {}", config.item))?;
+    let mut items = Vec::new();
+    for item in items_sel.iter() {
+        let title = item.try_select(&config.title).map(|t| t.first().text().trim().to_string()).unwrap_or_default();
+        let href = match &config.link {
+            Some(selector) => item.try_select(selector).and_then(|l| l.first().attr("href")),
+            None if item.is("a[href]") => item.attr("href"),
+            None => item.select("a[href]").first().attr("href"),
+        };
+        let Some(link) = href.and_then(|h| base.join(h.trim()).ok()) else {
+            continue;
+        };
+        if title.is_empty() {
+            continue;
+        }
+
+        let published = config
+            .date
+            .as_deref()
+            .and_then(|selector| date_text(&item, selector, config.date_attribute.as_deref()))
+            .and_then(|text| parse_date(&text, config.date_format.as_deref()))
+            .unwrap_or(fetched_at);
+        let summary = config.summary.as_deref().and_then(|selector| item.try_select(selector)).map(|s| s.first());
+
+        items.push(json!({
+            "id": link.as_str(),
+            "url": link.as_str(),
+            "title": title,
+            "date_published": published.to_rfc3339(),
+            "summary": summary.as_ref().map(|s| s.text().trim().to_string()).filter(|s| !s.is_empty()),
+            "content_html": summary.as_ref().map(|s| s.inner_html().trim().to_string()).filter(|s| !s.is_empty()),
+        }));
+    }
+
+    if items.is_empty() {
+        anyhow::bail!("Scrape config matched no items on {}", page_url);
+    }
+
+    let feed = json!({
+        "version": "https://jsonfeed.org/version/1.1",
+        "title": if page_title.is_empty() { page_url.to_string() } else { page_title },
+        "home_page_url": page_url,
+        "items": items,
+    });
+    Ok(parser::parse(feed.to_string().as_bytes())?)
+}
+
+fn date_text(item: &Selection, selector: &str, attribute: Option<&str>) -> Option<String> {
+    let element = item.try_select(selector)?.first();
+    if !element.exists() {
+        return None;
+    }
+    let text = match attribute {
+        Some(attr) => element.attr(attr)?.to_string(),
+        None => element.text().to_string(),
+    };
+    Some(text.trim().to_string())
+}
+
+pub fn parse_date(text: &str, format: Option<&str>) -> Option<DateTime<Utc>> {
+    if let Some(format) = format {
+        if let Ok(dt) = DateTime::parse_from_str(text, format) {
+            return Some(dt.with_timezone(&Utc));
+        }
+        if let Ok(dt) = NaiveDateTime::parse_from_str(text, format) {
+            return Some(dt.and_utc());
+        }
+        return NaiveDate::parse_from_str(text, format)
+            .ok()
+            .and_then(|d| d.and_hms_opt(0, 0, 0))
+            .map(|d| d.and_utc());
+    }
+    DateTime::parse_from_rfc3339(text)
+        .or_else(|_| DateTime::parse_from_rfc2822(text))
+        .map(|dt| dt.with_timezone(&Utc))
+        .ok()
+        .or_else(|| parse_date(text, Some("%Y-%m-%d")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LISTING: &str = r#"<html><head><title>Example News</title></head><body>
+        <article class="post">
+            <h2><a href="/2024/01/first">First post</a></h2>
+            <span class="date">March 5, 2024</span>
+            <p class="excerpt">The <b>first</b> one.</p>
+        </article>
+        <article class="post">
+            <h2><a href="https://other.example/second">Second post</a></h2>
+            <span class="date">not a date</span>
+        </article>
+        <article class="post"><h2>No link here</h2></article>
+    </body></html>"#;
+
+    #[test]
+    fn scrapes_listing_into_feed_entries() {
+        let config = parse_scrape_config(
+            "item: article.post\ntitle: h2\ndate: .date\ndate_format: '%B %d, %Y'\nsummary: .excerpt\n",
+        )
+        .unwrap();
+        let feed = scrape_listing(LISTING, "https://example.com/news/", &config).unwrap();
+
+        assert_eq!(feed.title.unwrap().content, "Example News");
+        assert_eq!(feed.entries.len(), 2);
+
+        let first = &feed.entries[0];
+        assert_eq!(first.title.as_ref().unwrap().content, "First post");
+        assert_eq!(first.links[0].href, "https://example.com/2024/01/first");
+        assert_eq!(first.published.unwrap().to_rfc3339(), "2024-03-05T00:00:00+00:00");
+        assert_eq!(first.summary.as_ref().unwrap().content, "The first one.");
+        assert!(first.content.as_ref().unwrap().body.as_deref().unwrap().contains("<b>first</b>"));
+
+        let second = &feed.entries[1];
+        assert_eq!(second.links[0].href, "https://other.example/second");
+        let published = second.published.unwrap();
+        assert!(published > Utc::now() - chrono::Duration::minutes(1) && published <= Utc::now());
+    }
+
+    #[test]
+    fn rejects_incomplete_config() {
+        assert!(parse_scrape_config("item: article\n").is_err());
+        assert!(parse_scrape_config("item: ''\ntitle: h2\n").is_err());
+        assert!(parse_scrape_config("item: article\ntitle: h2\nlink: .post >> a\n").is_err());
+        assert!(parse_scrape_config("item: article\ntitle: h2\ndate: 'time['\n").is_err());
+    }
+}
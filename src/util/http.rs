@@ -0,0 +1,287 @@
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use arc_swap::ArcSwap;
+use chrono::{DateTime, Utc};
+use encoding_rs::{Encoding, UTF_8};
+use reqwest::header::{self, HeaderMap};
+use reqwest::redirect::Policy;
+use reqwest::{Client, Proxy, RequestBuilder, Response, StatusCode};
+use tracing::warn;
+use crate::models::GeneralConfig;
+
+pub const DEFAULT_USER_AGENT: &str = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
+const BACKOFF_BASE: Duration = Duration::from_millis(500);
+/// Longest `Retry-After` that is waited for; a server asking for more gets its
+/// error response passed on instead.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+/// A whole request may take this many read timeouts (plus the connect timeout),
+/// so a server trickling bytes in under the read timeout is still cut off.
+const TOTAL_TIMEOUT_READS: u32 = 4;
+
+static HTTP_CLIENT: OnceLock<ArcSwap<HttpClient>> = OnceLock::new();
+
+/// Outgoing HTTP settings, taken from the general config.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpSettings {
+    pub user_agent: String,
+    pub proxy: Option<String>,
+    pub connect_timeout: Duration,
+    pub read_timeout: Duration,
+    pub max_response_bytes: Option<usize>,
+    pub max_redirects: usize,
+    pub max_retries: u32,
+}
+
+impl Default for HttpSettings {
+    fn default() -> Self {
+        Self {
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            proxy: None,
+            connect_timeout: Duration::from_secs(10),
+            read_timeout: Duration::from_secs(45),
+            max_response_bytes: Some(20 * 1024 * 1024),
+            max_redirects: 10,
+            max_retries: 2,
+        }
+    }
+}
+
+impl From<&GeneralConfig> for HttpSettings {
+    fn from(config: &GeneralConfig) -> Self {
+        let user_agent = config.http_user_agent.trim();
+        let proxy = config.http_proxy.trim();
+        Self {
+            user_agent: if user_agent.is_empty() { DEFAULT_USER_AGENT.to_string() } else { user_agent.to_string() },
+            proxy: (!proxy.is_empty()).then(|| proxy.to_string()),
+            connect_timeout: Duration::from_secs(config.http_connect_timeout_seconds.max(1) as u64),
+            read_timeout: Duration::from_secs(config.http_read_timeout_seconds.max(1) as u64),
+            max_response_bytes: (config.http_max_response_mb > 0)
+                .then(|| config.http_max_response_mb as usize * 1024 * 1024),
+            max_redirects: config.http_max_redirects.max(0) as usize,
+            max_retries: config.http_max_retries.max(0) as u32,
+        }
+    }
+}
+
+/// The client every outgoing request goes through. Clones share one connection
+/// pool. The shared client keeps no cookies; see [`HttpClient::with_cookie_jar`].
+#[derive(Clone)]
+pub struct HttpClient {
+    client: Client,
+    settings: HttpSettings,
+    max_response_bytes: Option<usize>,
+    max_retries: u32,
+}
+
+impl HttpClient {
+    pub fn new(settings: &HttpSettings) -> reqwest::Result<Self> {
+        Self::build(settings, false)
+    }
+
+    /// A client with the same settings and a cookie jar of its own, for one
+    /// generation run: cookies set while fetching (e.g. on redirects) are sent
+    /// back within the run and dropped with it.
+    pub fn with_cookie_jar(&self) -> HttpClient {
+        Self::build(&self.settings, true).unwrap_or_else(|e| {
+            warn!("Failed to create HTTP client with cookie jar: {}", e);
+            self.clone()
+        })
+    }
+
+    fn build(settings: &HttpSettings, cookie_store: bool) -> reqwest::Result<Self> {
+        let redirect = if settings.max_redirects == 0 {
+            Policy::none()
+        } else {
+            Policy::limited(settings.max_redirects)
+        };
+        let mut builder = Client::builder()
+            .user_agent(settings.user_agent.as_str())
+            .connect_timeout(settings.connect_timeout)
+            .read_timeout(settings.read_timeout)
+            .timeout(settings.connect_timeout + settings.read_timeout * TOTAL_TIMEOUT_READS)
+            .redirect(redirect)
+            .cookie_store(cookie_store);
+        if let Some(proxy) = &settings.proxy {
+            builder = builder.proxy(Proxy::all(proxy.as_str())?);
+        }
+
+        Ok(Self {
+            client: builder.build()?,
+            settings: settings.clone(),
+            max_response_bytes: settings.max_response_bytes,
+            max_retries: settings.max_retries,
+        })
+    }
+
+    pub fn get(&self, url: &str) -> RequestBuilder {
+        self.client.get(url)
+    }
+
+    /// Sends a request, retrying connection errors, timeouts and 429/502/503/504
+    /// responses with exponential backoff. `Retry-After` is honoured on 429 and 503.
+    pub async fn send(&self, request: RequestBuilder) -> reqwest::Result<Response> {
+        let mut attempt = 0;
+        loop {
+            let Some(this_try) = request.try_clone() else {
+                return request.send().await;
+            };
+            let result = this_try.send().await;
+            if attempt >= self.max_retries {
+                return result;
+            }
+
+            let (url, delay) = match &result {
+                Ok(resp) if is_retryable_status(resp.status()) => match retry_after(resp) {
+                    Some(delay) if delay > MAX_RETRY_AFTER => return result,
+                    Some(delay) => (resp.url().to_string(), delay),
+                    None => (resp.url().to_string(), backoff(attempt)),
+                },
+                Err(e) if e.is_connect() || e.is_timeout() => {
+                    (e.url().map(|u| u.to_string()).unwrap_or_default(), backoff(attempt))
+                }
+                _ => return result,
+            };
+            attempt += 1;
+            warn!("Retrying {} in {:?} (attempt {} of {})", url, delay, attempt, self.max_retries);
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// GETs `url` with the given extra headers and returns the URL it ended up
+    /// at after redirects, with the decoded body.
+    pub async fn get_page(&self, url: &str, headers: &HeaderMap) -> anyhow::Result<(String, String)> {
+        let resp = self.send(self.get(url).headers(headers.clone())).await?;
+        let final_url = resp.url().to_string();
+        Ok((final_url, self.text(resp).await?))
+    }
+
+    /// Fails once `received` bytes exceed the configured response size limit.
+    pub fn check_size(&self, received: usize) -> anyhow::Result<()> {
+        match self.max_response_bytes {
+            Some(max) if received > max => anyhow::bail!("Response exceeds the {} byte limit", max),
+            _ => Ok(()),
+        }
+    }
+
+    pub async fn bytes(&self, mut resp: Response) -> anyhow::Result<Vec<u8>> {
+        if let Some(len) = resp.content_length() {
+            self.check_size(len as usize)?;
+        }
+        let mut body = Vec::new();
+        while let Some(chunk) = resp.chunk().await? {
+            body.extend_from_slice(&chunk);
+            self.check_size(body.len())?;
+        }
+        Ok(body)
+    }
+
+    /// Reads the body as text, decoding it with the charset from `Content-Type`.
+    pub async fn text(&self, resp: Response) -> anyhow::Result<String> {
+        let encoding = resp
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(charset)
+            .and_then(|label| Encoding::for_label(label.as_bytes()))
+            .unwrap_or(UTF_8);
+        let body = self.bytes(resp).await?;
+        let (text, _, _) = encoding.decode(&body);
+        Ok(text.into_owned())
+    }
+}
+
+/// Rebuilds the shared client from the general config.
+pub fn configure(config: &GeneralConfig) -> reqwest::Result<()> {
+    let client = HttpClient::new(&HttpSettings::from(config))?;
+    match HTTP_CLIENT.get() {
+        Some(swap) => swap.store(Arc::new(client)),
+        None => {
+            let _ = HTTP_CLIENT.set(ArcSwap::from_pointee(client));
+        }
+    }
+    Ok(())
+}
+
+/// The shared client. A generation run takes it once so all of its requests use
+/// the same settings and connections.
+pub fn client() -> HttpClient {
+    HTTP_CLIENT
+        .get_or_init(|| {
+            let client = HttpClient::new(&HttpSettings::default()).unwrap_or_else(|_| HttpClient {
+                client: Client::new(),
+                settings: HttpSettings::default(),
+                max_response_bytes: None,
+                max_retries: 0,
+            });
+            ArcSwap::from_pointee(client)
+        })
+        .load()
+        .as_ref()
+        .clone()
+}
+
+fn is_retryable_status(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+fn backoff(attempt: u32) -> Duration {
+    BACKOFF_BASE * 2u32.saturating_pow(attempt)
+}
+
+fn retry_after(resp: &Response) -> Option<Duration> {
+    if !matches!(resp.status(), StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE) {
+        return None;
+    }
+    let value = resp.headers().get(header::RETRY_AFTER)?.to_str().ok()?;
+    parse_retry_after(value, Utc::now())
+}
+
+/// `Retry-After` is either a number of seconds or an HTTP date.
+fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?.with_timezone(&Utc);
+    Some((date - now).to_std().unwrap_or(Duration::ZERO))
+}
+
+fn charset(content_type: &str) -> Option<String> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        name.trim()
+            .eq_ignore_ascii_case("charset")
+            .then(|| value.trim().trim_matches('"').to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn retry_after_accepts_seconds_and_dates() {
+        let now = Utc.with_ymd_and_hms(2015, 10, 21, 7, 27, 30).unwrap();
+        assert_eq!(parse_retry_after("120", now), Some(Duration::from_secs(120)));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT", now),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:00:00 GMT", now), Some(Duration::ZERO));
+        assert_eq!(parse_retry_after("soon", now), None);
+        assert_eq!(backoff(2), Duration::from_secs(2));
+    }
+
+    #[test]
+    fn charset_is_read_from_content_type() {
+        assert_eq!(charset("text/html; charset=\"ISO-8859-1\"").as_deref(), Some("ISO-8859-1"));
+        assert_eq!(charset("text/html"), None);
+    }
+}
@@ -0,0 +1,215 @@
+use dom_query::{Document, NodeRef};
+
+use crate::util::urls;
+
+/// Width images are scaled to for the e-reader; responsive candidates are picked for it.
+pub const TARGET_IMAGE_WIDTH: u32 = 600;
+
+/// Attributes lazy-loading scripts keep the real image URL in, most specific first.
+pub(crate) const LAZY_SRC_ATTRS: &[&str] = &[
+    "data-lazy-src",
+    "data-src",
+    "data-original",
+    "data-lazy",
+    "data-url",
+    "data-hi-res-src",
+];
+pub(crate) const LAZY_SRCSET_ATTRS: &[&str] = &["data-lazy-srcset", "data-srcset", "srcset"];
+/// Formats the image pipeline cannot decode; such `<source>` elements are skipped.
+const UNSUPPORTED_TYPES: &[&str] = &["image/avif", "image/jxl", "image/heic", "image/svg+xml"];
+
+/// Rewrites lazy-loaded and responsive images into plain `<img src>` tags pointing
+/// at the best real image for [`TARGET_IMAGE_WIDTH`], so `process_images` finds them.
+/// `<noscript>` fallbacks replace their placeholder and `<picture>` elements are
+/// reduced to their `<img>`.
+pub fn normalize_images(html: &str) -> String {
+    if !html.contains("<img") && !html.contains("<picture") && !html.contains("<noscript") {
+        return html.to_string();
+    }
+    let doc = Document::from(html);
+
+    for noscript in doc.select("noscript").iter() {
+        // Depending on the parser the fallback is markup or raw text.
+        let fallback = if noscript.select("img").exists() {
+            noscript.inner_html().to_string()
+        } else {
+            noscript.text().to_string()
+        };
+        if !fallback.contains("<img") {
+            continue;
+        }
+        let Some(noscript) = noscript.nodes().first().copied() else {
+            continue;
+        };
+        // The element before a fallback is usually the script-driven placeholder.
+        if let Some(prev) = noscript.prev_element_sibling()
+            && prev.node_name().as_deref() == Some("img")
+        {
+            prev.remove_from_parent();
+        }
+        noscript.replace_with_html(fallback);
+    }
+
+    for picture in doc.select("picture").iter() {
+        let Some(img) = picture.select("img").nodes().first().copied() else {
+            picture.remove();
+            continue;
+        };
+        let mut candidates = Vec::new();
+        for source in picture.select("source").nodes() {
+            let supported = source
+                .attr("type")
+                .is_none_or(|t| !UNSUPPORTED_TYPES.contains(&t.trim().to_ascii_lowercase().as_str()));
+            if supported && let Some(srcset) = srcset_of(source) {
+                candidates.extend(parse_srcset(&srcset));
+            }
+        }
+        if let Some(best) = pick_candidate(&candidates) {
+            img.set_attr("src", &best);
+            img.remove_attr("srcset");
+        }
+        picture.replace_with_html(img.html());
+    }
+
+    for img in doc.select("img").nodes() {
+        if let Some(src) = best_source(img) {
+            img.set_attr("src", &urls::strip_tracking_params(&src));
+        }
+        img.remove_attrs(&["srcset", "sizes", "loading"]);
+        for attr in LAZY_SRC_ATTRS.iter().chain(LAZY_SRCSET_ATTRS) {
+            img.remove_attr(attr);
+        }
+    }
+
+    doc.select("body").inner_html().to_string()
+}
+
+fn best_source(img: &NodeRef) -> Option<String> {
+    let from_srcset = srcset_of(img).and_then(|srcset| pick_candidate(&parse_srcset(&srcset)));
+    let lazy = || {
+        LAZY_SRC_ATTRS
+            .iter()
+            .filter_map(|attr| img.attr(attr))
+            .map(|v| v.trim().to_string())
+            .find(|v| !is_placeholder(v))
+    };
+    let src = img.attr("src").map(|v| v.trim().to_string()).filter(|v| !is_placeholder(v));
+    from_srcset.or_else(lazy).or(src)
+}
+
+fn srcset_of(node: &NodeRef) -> Option<String> {
+    LAZY_SRCSET_ATTRS
+        .iter()
+        .filter_map(|attr| node.attr(attr))
+        .map(|v| v.to_string())
+        .find(|v| !v.trim().is_empty())
+}
+
+fn is_placeholder(src: &str) -> bool {
+    src.is_empty() || src.starts_with("data:") || src == "about:blank"
+}
+
+#[derive(Debug, PartialEq)]
+enum Descriptor {
+    Width(u32),
+    Density(f32),
+}
+
+/// Splits `srcset` into URL and raw descriptor pairs. Follows the HTML tokenizing
+/// rules: URLs run to whitespace and may contain commas, descriptors run to the
+/// next comma.
+fn split_srcset(srcset: &str) -> Vec<(&str, &str)> {
+    let mut entries = Vec::new();
+    let mut rest = srcset;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+        if rest.is_empty() {
+            break;
+        }
+        let url_end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        let (raw_url, after) = rest.split_at(url_end);
+        if raw_url.ends_with(',') {
+            rest = after;
+            entries.push((raw_url.trim_end_matches(','), ""));
+        } else {
+            let descriptor_end = after.find(',').unwrap_or(after.len());
+            rest = &after[descriptor_end..];
+            entries.push((raw_url, after[..descriptor_end].trim()));
+        }
+    }
+    entries
+}
+
+/// Parses `srcset` into URLs and descriptors; entries without one count as `1x`.
+fn parse_srcset(srcset: &str) -> Vec<(String, Descriptor)> {
+    let mut candidates = Vec::new();
+    for (url, descriptor) in split_srcset(srcset) {
+        let descriptor = match descriptor {
+            "" => Some(Descriptor::Density(1.0)),
+            d if d.ends_with('w') => d.trim_end_matches('w').parse().ok().map(Descriptor::Width),
+            d if d.ends_with('x') => d.trim_end_matches('x').parse().ok().map(Descriptor::Density),
+            _ => None,
+        };
+        if !is_placeholder(url)
+            && let Some(descriptor) = descriptor
+        {
+            candidates.push((url.to_string(), descriptor));
+        }
+    }
+    candidates
+}
+
+/// Rewrites every URL of a `srcset` with `f`, keeping the descriptors.
+pub(crate) fn map_srcset(srcset: &str, f: impl Fn(&str) -> String) -> String {
+    split_srcset(srcset)
+        .into_iter()
+        .map(|(url, descriptor)| match descriptor {
+            "" => f(url),
+            d => format!("{} {}", f(url), d),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The smallest candidate at least as wide as the target (or `1x`), otherwise the largest.
+fn pick_candidate(candidates: &[(String, Descriptor)]) -> Option<String> {
+    let mut widths: Vec<(u32, &str)> = Vec::new();
+    let mut densities: Vec<(f32, &str)> = Vec::new();
+    for (url, descriptor) in candidates {
+        match descriptor {
+            Descriptor::Width(w) => widths.push((*w, url)),
+            Descriptor::Density(x) => densities.push((*x, url)),
+        }
+    }
+    if !widths.is_empty() {
+        widths.sort_by_key(|(w, _)| *w);
+        let best = widths.iter().find(|(w, _)| *w >= TARGET_IMAGE_WIDTH).or(widths.last());
+        return best.map(|(_, url)| url.to_string());
+    }
+    densities.sort_by(|a, b| a.0.total_cmp(&b.0));
+    densities
+        .iter()
+        .find(|(x, _)| *x >= 1.0)
+        .or(densities.last())
+        .map(|(_, url)| url.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn srcset_picks_smallest_candidate_covering_target() {
+        let widths = parse_srcset("a.jpg 300w, b.jpg 640w, c.jpg 1200w");
+        assert_eq!(pick_candidate(&widths).as_deref(), Some("b.jpg"));
+        let small = parse_srcset("a.jpg 200w,b.jpg 400w");
+        assert_eq!(pick_candidate(&small).as_deref(), Some("b.jpg"));
+        let densities = parse_srcset("a.jpg, b.jpg 2x");
+        assert_eq!(pick_candidate(&densities).as_deref(), Some("a.jpg"));
+        let commas = parse_srcset("https://cdn.example.com/i/w_300,h_200/a.jpg 300w, https://cdn.example.com/i/w_800,h_600/a.jpg 800w");
+        assert_eq!(
+            pick_candidate(&commas).as_deref(),
+            Some("https://cdn.example.com/i/w_800,h_600/a.jpg")
+        );
+    }
+}
@@ -0,0 +1,61 @@
+use dom_query::Document;
+
+/// Replaces every external link in `html` with its text followed by a numbered
+/// superscript `noteref`, and returns the linked URLs in note order. Repeated
+/// URLs share a number. The chapter template renders the matching footnotes.
+pub fn links_to_noterefs(html: &str) -> (String, Vec<String>) {
+    let mut notes: Vec<String> = Vec::new();
+    if !html.contains("href=") {
+        return (html.to_string(), notes);
+    }
+    let doc = Document::from(html);
+    for link in doc.select("a[href]").nodes() {
+        let Some(href) = link.attr("href").map(|h| h.trim().to_string()) else {
+            continue;
+        };
+        if !href.starts_with("http://") && !href.starts_with("https://") {
+            continue;
+        }
+        let (number, first) = match notes.iter().position(|n| *n == href) {
+            Some(i) => (i + 1, false),
+            None => {
+                notes.push(href);
+                (notes.len(), true)
+            }
+        };
+        // Only the first reference carries the id the footnote links back to.
+        let id = if first { format!(" id=\"link-ref-{}\"", number) } else { String::new() };
+        let noteref = format!(
+            "{}<sup><a epub:type=\"noteref\" href=\"#link-{n}\"{}>{n}</a></sup>",
+            link.inner_html(),
+            id,
+            n = number
+        );
+        link.replace_with_html(noteref);
+    }
+    if notes.is_empty() {
+        return (html.to_string(), notes);
+    }
+    (doc.select("body").inner_html().to_string(), notes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn external_links_become_numbered_noterefs() {
+        let (html, notes) = links_to_noterefs(
+            r##"<p>See <a href="https://example.com/a">this</a>, <a href="#top">top</a>,
+            <a href="https://example.com/b"><em>that</em></a> and <a href="https://example.com/a">again</a>.</p>"##,
+        );
+        assert_eq!(notes, ["https://example.com/a", "https://example.com/b"]);
+        assert!(html.contains(r##"this<sup><a epub:type="noteref" href="#link-1" id="link-ref-1">1</a></sup>"##));
+        assert!(html.contains(r##"<em>that</em><sup><a epub:type="noteref" href="#link-2" id="link-ref-2">2</a></sup>"##));
+        assert!(html.contains(r##"again<sup><a epub:type="noteref" href="#link-1">1</a></sup>"##));
+        assert!(html.contains(r##"<a href="#top">top</a>"##));
+
+        let plain = "<p>No links here</p>";
+        assert_eq!(links_to_noterefs(plain), (plain.to_string(), Vec::new()));
+    }
+}
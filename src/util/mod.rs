@@ -1,35 +1,74 @@
+use std::collections::HashSet;
 use std::sync::LazyLock;
 use regex::Regex;
 use ammonia::Builder;
+use crate::models::HtmlProfile;
+pub(crate) mod article_cache;
 pub(crate) mod content_extractors;
+pub(crate) mod ftr_import;
+pub(crate) mod html_feed;
+pub(crate) mod http;
+pub(crate) mod lazy_images;
+pub(crate) mod link_notes;
+pub(crate) mod secrets;
+pub(crate) mod urls;
+pub(crate) mod xpath;
+
+pub use lazy_images::normalize_images;
 
 pub const EPUB_OUTPUT_DIR: &str = "epubs";
 pub const COVER_LOCATION: &str = "static/cover.jpg";
-pub fn clean_html(html: &str) -> String {
+/// Tags every sanitizer profile keeps.
+const BASIC_TAGS: &[&str] = &[
+    "img", "p", "br", "b", "i", "strong", "em", "h1", "h2", "h3", "h4", "h5", "h6",
+    "ul", "ol", "li", "blockquote", "hr", "a", "div", "span",
+];
+
+/// Tables, code, figures, definition lists and inline semantics kept by the
+/// extended profile. Each has an XHTML form `fix_xhtml` can produce.
+const EXTENDED_TAGS: &[&str] = &[
+    "table", "caption", "colgroup", "col", "thead", "tbody", "tfoot", "tr", "th", "td",
+    "pre", "code", "kbd", "samp", "var", "figure", "figcaption", "sup", "sub",
+    "dl", "dt", "dd", "abbr", "cite", "q", "small", "mark", "s", "del", "ins", "u",
+    "time", "wbr", "section", "article", "aside",
+];
+
+/// Presentation MathML, for readers that render it.
+const MATHML_TAGS: &[&str] = &[
+    "math", "mrow", "mi", "mo", "mn", "ms", "mtext", "mspace", "msup", "msub", "msubsup",
+    "mfrac", "msqrt", "mroot", "mover", "munder", "munderover", "mtable", "mtr", "mtd",
+    "mstyle", "mpadded", "mphantom", "menclose", "semantics", "annotation",
+];
+
+const MATHML_ATTRIBUTES: &[(&str, &[&str])] = &[
+    ("math", &["display"]),
+    ("mi", &["mathvariant"]),
+    ("mo", &["stretchy", "fence", "separator", "lspace", "rspace", "largeop", "movablelimits"]),
+    ("mspace", &["width", "height", "depth"]),
+    ("mfrac", &["linethickness"]),
+    ("mover", &["accent"]),
+    ("munder", &["accentunder"]),
+    ("munderover", &["accent", "accentunder"]),
+    ("mtable", &["columnalign", "rowalign"]),
+    ("mtd", &["columnspan", "rowspan", "columnalign"]),
+    ("mstyle", &["displaystyle", "scriptlevel", "mathvariant"]),
+    ("mpadded", &["width", "height", "depth", "lspace", "voffset"]),
+    ("menclose", &["notation"]),
+    ("annotation", &["encoding"]),
+];
+
+pub fn clean_html(html: &str, profile: HtmlProfile) -> String {
     let mut builder = Builder::new();
-    builder.add_tags(&[
-        "img",
-        "p",
-        "br",
-        "b",
-        "i",
-        "strong",
-        "em",
-        "h1",
-        "h2",
-        "h3",
-        "h4",
-        "h5",
-        "h6",
-        "ul",
-        "ol",
-        "li",
-        "blockquote",
-        "hr",
-        "a",
-        "div",
-        "span",
-    ]);
+    let mut tags: HashSet<&str> = BASIC_TAGS.iter().copied().collect();
+    if profile == HtmlProfile::Extended {
+        tags.extend(EXTENDED_TAGS);
+        tags.extend(MATHML_TAGS);
+        builder.add_tag_attributes("time", &["datetime"]);
+        for (tag, attributes) in MATHML_ATTRIBUTES {
+            builder.add_tag_attributes(tag, *attributes);
+        }
+    }
+    builder.tags(tags);
     builder.add_generic_attributes(&["src", "href", "alt", "title", "class", "id"]);
     builder.clean(html).to_string()
 }
@@ -69,6 +108,13 @@ pub fn fix_xhtml(html: &str) -> String {
     fixed = BR_REGEX.replace_all(&fixed, "<br />").to_string();
     static HR_REGEX: LazyLock<Regex> =LazyLock::new(|| Regex::new(r"<hr\s*>").unwrap());
     fixed = HR_REGEX.replace_all(&fixed, "<hr />").to_string();
+    static VOID_REGEX: LazyLock<Regex> =LazyLock::new(|| Regex::new(r"<(col|wbr)\b([^>]*?)\s*/?>").unwrap());
+    fixed = VOID_REGEX.replace_all(&fixed, "<$1$2 />").to_string();
+    // The HTML serializer leaves out the namespace XHTML readers need to see MathML.
+    static MATH_REGEX: LazyLock<Regex> =LazyLock::new(|| Regex::new(r"<math\b").unwrap());
+    fixed = MATH_REGEX
+        .replace_all(&fixed, r#"<math xmlns="http://www.w3.org/1998/Math/MathML""#)
+        .to_string();
 
     fixed
 }
\ No newline at end of file
@@ -0,0 +1,93 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::digest::{digest, SHA256};
+use ring::rand::{SecureRandom, SystemRandom};
+
+/// Passphrase used to encrypt stored feed credentials. Without it none can be
+/// stored.
+pub const SECRET_KEY_ENV: &str = "RSSPUB_SECRET_KEY";
+const ENCRYPTED_PREFIX: &str = "enc:v1:";
+
+fn secret_key() -> Option<String> {
+    std::env::var(SECRET_KEY_ENV).ok().filter(|k| !k.is_empty())
+}
+
+pub fn has_secret_key() -> bool {
+    secret_key().is_some()
+}
+
+/// Encrypts a secret for storage; fails when no secret key is configured.
+pub fn seal(plaintext: &str) -> anyhow::Result<String> {
+    seal_with(secret_key().as_deref(), plaintext)
+}
+
+/// Reverses [`seal`]. Plain text values stored before a key was configured are
+/// returned as is.
+pub fn open(stored: &str) -> anyhow::Result<String> {
+    open_with(secret_key().as_deref(), stored)
+}
+
+fn aead_key(passphrase: &str) -> anyhow::Result<LessSafeKey> {
+    let key_bytes = digest(&SHA256, passphrase.as_bytes());
+    let key = UnboundKey::new(&AES_256_GCM, key_bytes.as_ref())
+        .map_err(|_| anyhow::anyhow!("Invalid secret key"))?;
+    Ok(LessSafeKey::new(key))
+}
+
+fn seal_with(passphrase: Option<&str>, plaintext: &str) -> anyhow::Result<String> {
+    let Some(passphrase) = passphrase else {
+        anyhow::bail!("{} is not set; secrets are never stored unencrypted", SECRET_KEY_ENV);
+    };
+    let key = aead_key(passphrase)?;
+    let mut nonce = [0u8; NONCE_LEN];
+    SystemRandom::new()
+        .fill(&mut nonce)
+        .map_err(|_| anyhow::anyhow!("Failed to generate nonce"))?;
+
+    let mut in_out = plaintext.as_bytes().to_vec();
+    key.seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut in_out)
+        .map_err(|_| anyhow::anyhow!("Failed to encrypt secret"))?;
+
+    let mut sealed = nonce.to_vec();
+    sealed.extend_from_slice(&in_out);
+    Ok(format!("{}{}", ENCRYPTED_PREFIX, STANDARD.encode(sealed)))
+}
+
+fn open_with(passphrase: Option<&str>, stored: &str) -> anyhow::Result<String> {
+    let Some(encoded) = stored.strip_prefix(ENCRYPTED_PREFIX) else {
+        return Ok(stored.to_string());
+    };
+    let passphrase = passphrase
+        .ok_or_else(|| anyhow::anyhow!("Secret is encrypted but {} is not set", SECRET_KEY_ENV))?;
+    let sealed = STANDARD.decode(encoded)?;
+    if sealed.len() < NONCE_LEN {
+        anyhow::bail!("Encrypted secret is truncated");
+    }
+
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| anyhow::anyhow!("Invalid nonce"))?;
+    let mut in_out = ciphertext.to_vec();
+    let plaintext = aead_key(passphrase)?
+        .open_in_place(nonce, Aad::empty(), &mut in_out)
+        .map_err(|_| anyhow::anyhow!("Failed to decrypt secret, was {} changed?", SECRET_KEY_ENV))?;
+    Ok(String::from_utf8(plaintext.to_vec())?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sealed_secrets_round_trip_only_with_the_same_key() {
+        let sealed = seal_with(Some("passphrase"), "token-123").unwrap();
+        assert!(sealed.starts_with(ENCRYPTED_PREFIX));
+        assert!(!sealed.contains("token-123"));
+        assert_eq!(open_with(Some("passphrase"), &sealed).unwrap(), "token-123");
+        assert!(open_with(Some("other"), &sealed).is_err());
+        assert!(open_with(None, &sealed).is_err());
+
+        assert!(seal_with(None, "plain").is_err());
+        assert_eq!(open_with(Some("passphrase"), "plain").unwrap(), "plain");
+    }
+}
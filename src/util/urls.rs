@@ -0,0 +1,311 @@
+use std::sync::{Arc, OnceLock};
+
+use arc_swap::ArcSwap;
+use dom_query::Document;
+use url::Url;
+
+use crate::util::lazy_images::{LAZY_SRC_ATTRS, LAZY_SRCSET_ATTRS, map_srcset};
+
+/// Query parameters that only carry campaign or referral tracking; a trailing
+/// `*` matches any parameter starting with the rest.
+pub const DEFAULT_TRACKING_PARAMS: &[&str] = &[
+    "utm_*", "fbclid", "gclid", "dclid", "msclkid", "mc_cid", "mc_eid", "igshid", "yclid",
+    "ref_src", "ref_url", "_hsenc", "_hsmi", "cmpid", "ncid",
+];
+
+static TRACKING_PARAMS: OnceLock<ArcSwap<Vec<String>>> = OnceLock::new();
+
+fn tracking_params() -> &'static ArcSwap<Vec<String>> {
+    TRACKING_PARAMS.get_or_init(|| {
+        ArcSwap::from_pointee(DEFAULT_TRACKING_PARAMS.iter().map(|p| p.to_string()).collect())
+    })
+}
+
+/// Replaces the tracking parameters stripped from links, from the general config.
+pub fn configure_tracking_params(params: &[String]) {
+    let params = params
+        .iter()
+        .map(|p| p.trim().to_ascii_lowercase())
+        .filter(|p| !p.is_empty())
+        .collect();
+    tracking_params().store(Arc::new(params));
+}
+
+fn is_tracking_param(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    tracking_params().load().iter().any(|param| match param.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => name == *param,
+    })
+}
+
+/// Normalizes an article link so the same story linked from different feeds compares equal.
+/// Scheme, `www.`, fragment, trailing slash and tracking parameters are ignored; the
+/// remaining query parameters are sorted. Returns `None` for links that do not parse.
+pub fn normalize_url(link: &str) -> Option<String> {
+    let url = Url::parse(link.trim()).ok()?;
+    let host = url.host_str()?.to_lowercase();
+    let host = host.strip_prefix("www.").unwrap_or(&host);
+
+    let mut params: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(k, _)| !is_tracking_param(k))
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect();
+    params.sort();
+
+    let path = url.path().trim_end_matches('/');
+    let mut normalized = match url.port() {
+        Some(port) => format!("{}:{}{}", host, port, path),
+        None => format!("{}{}", host, path),
+    };
+    if !params.is_empty() {
+        let query = url::form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(params)
+            .finish();
+        normalized.push('?');
+        normalized.push_str(&query);
+    }
+    Some(normalized)
+}
+
+/// `link` without tracking parameters and with AMP and mobile variants mapped
+/// back to the regular page. Links that do not parse are returned unchanged.
+pub fn clean_url(link: &str) -> String {
+    let Ok(url) = Url::parse(link.trim()) else {
+        return link.to_string();
+    };
+    if !matches!(url.scheme(), "http" | "https") {
+        return link.to_string();
+    }
+    let mut url = unwrap_amp_cache(&url).unwrap_or(url);
+    normalize_mobile_host(&mut url);
+    strip_amp_path(&mut url);
+    retain_query(&mut url, |k, v| !is_tracking_param(k) && !is_amp_param(k, v));
+    url.to_string()
+}
+
+/// `link` without tracking parameters, for links such as images where the
+/// host and path must stay as they are.
+pub fn strip_tracking_params(link: &str) -> String {
+    match Url::parse(link.trim()) {
+        Ok(mut url) if matches!(url.scheme(), "http" | "https") && url.query().is_some() => {
+            retain_query(&mut url, |k, _| !is_tracking_param(k));
+            url.to_string()
+        }
+        _ => link.to_string(),
+    }
+}
+
+fn retain_query(url: &mut Url, keep: impl Fn(&str, &str) -> bool) {
+    let params: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(k, v)| keep(k, v))
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect();
+    if params.is_empty() {
+        url.set_query(None);
+    } else if params.len() != url.query_pairs().count() {
+        url.query_pairs_mut().clear().extend_pairs(params);
+    }
+}
+
+/// The page behind an AMP cache URL such as
+/// `https://example-com.cdn.ampproject.org/c/s/example.com/story` or
+/// `https://www.google.com/amp/s/example.com/story`.
+fn unwrap_amp_cache(url: &Url) -> Option<Url> {
+    let host = url.host_str()?;
+    let path = if host.ends_with(".cdn.ampproject.org") {
+        let path = url.path().trim_start_matches('/');
+        // Content, viewer and image variants share the same layout.
+        ["c/", "v/", "i/"].iter().find_map(|p| path.strip_prefix(p))?
+    } else if host.starts_with("www.google.") {
+        url.path().strip_prefix("/amp/")?
+    } else {
+        return None;
+    };
+    let (scheme, rest) = match path.strip_prefix("s/") {
+        Some(rest) => ("https", rest),
+        None => ("http", path),
+    };
+    let mut inner = Url::parse(&format!("{}://{}", scheme, rest)).ok()?;
+    inner.set_query(url.query());
+    Some(inner)
+}
+
+/// `amp.example.com`, `m.example.com` and `mobile.example.com` become
+/// `example.com`; an inner `m` label (`en.m.example.org`) is dropped too.
+fn normalize_mobile_host(url: &mut Url) {
+    let Some(host) = url.host_str() else {
+        return;
+    };
+    let labels: Vec<&str> = host.split('.').collect();
+    // The last two labels are the site's own domain and are always kept.
+    let kept: Vec<&str> = labels
+        .iter()
+        .enumerate()
+        .filter(|&(i, label)| {
+            i + 2 >= labels.len() || !(*label == "m" || (i == 0 && matches!(*label, "amp" | "mobile")))
+        })
+        .map(|(_, label)| *label)
+        .collect();
+    if kept.len() != labels.len() {
+        let _ = url.set_host(Some(&kept.join(".")));
+    }
+}
+
+/// Removes a leading or trailing `amp` path segment and the `.amp` in `story.amp.html`.
+fn strip_amp_path(url: &mut Url) {
+    let path = url.path();
+    let mut stripped = match path.strip_suffix("/amp/") {
+        Some(rest) => format!("{}/", rest),
+        None => path.strip_suffix("/amp").unwrap_or(path).to_string(),
+    };
+    if let Some(rest) = stripped.strip_prefix("/amp/")
+        && !rest.is_empty()
+    {
+        stripped = format!("/{}", rest);
+    }
+    let stripped = stripped.replacen(".amp.html", ".html", 1);
+    if stripped != path && !stripped.is_empty() {
+        url.set_path(&stripped);
+    }
+}
+
+fn is_amp_param(name: &str, value: &str) -> bool {
+    name == "amp" || (name.eq_ignore_ascii_case("outputtype") && value.eq_ignore_ascii_case("amp"))
+}
+
+/// The page's `<link rel="canonical">`, cleaned, when it names another HTTP(S)
+/// page. A canonical pointing at the site's front page from an article is
+/// ignored, as some sites set that on every page.
+pub fn canonical_link(html: &str, page_url: &str) -> Option<String> {
+    if !html.contains("canonical") {
+        return None;
+    }
+    let base = document_base(html, page_url)?;
+    let href = Document::from(html)
+        .select(r#"link[rel~="canonical"][href]"#)
+        .attr("href")?;
+    let canonical = base.join(href.trim()).ok()?;
+    if !matches!(canonical.scheme(), "http" | "https") || canonical.host_str().is_none() {
+        return None;
+    }
+    if canonical.path() == "/" && base.path() != "/" {
+        return None;
+    }
+    Some(clean_url(canonical.as_str()))
+}
+
+/// Attributes holding a single URL that extracted content may carry.
+const URL_ATTRS: &[&str] = &["href", "src", "poster", "cite"];
+
+/// The URL relative links in a page resolve against: its `<base href>` if it
+/// has one, otherwise the page's own URL.
+pub fn document_base(html: &str, page_url: &str) -> Option<Url> {
+    let page = Url::parse(page_url).ok()?;
+    let base = Document::from(html)
+        .select("base[href]")
+        .attr("href")
+        .and_then(|href| page.join(href.trim()).ok());
+    Some(base.unwrap_or(page))
+}
+
+/// Resolves every link, image and `srcset` candidate in `html` against `base`.
+/// Fragment-only links and non-HTTP schemes such as `data:` or `mailto:` are left alone.
+pub fn absolutize_urls(html: &str, base: &Url) -> String {
+    if !["href=", "src=", "srcset=", "poster=", "cite="].iter().any(|a| html.contains(a)) {
+        return html.to_string();
+    }
+    let resolve = |value: &str| -> String {
+        let value = value.trim();
+        if value.is_empty() || value.starts_with('#') {
+            return value.to_string();
+        }
+        base.join(value).map(String::from).unwrap_or_else(|_| value.to_string())
+    };
+
+    let doc = Document::from(html);
+    for node in doc.select("*").nodes() {
+        for attr in URL_ATTRS.iter().chain(LAZY_SRC_ATTRS) {
+            if let Some(value) = node.attr(attr) {
+                node.set_attr(attr, &resolve(&value));
+            }
+        }
+        for attr in LAZY_SRCSET_ATTRS {
+            if let Some(value) = node.attr(attr) {
+                node.set_attr(attr, &map_srcset(&value, resolve));
+            }
+        }
+    }
+    doc.select("body").inner_html().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relative_urls_resolve_against_base() {
+        let base = document_base(
+            "<html><head></head><body></body></html>",
+            "https://example.com/blog/2024/post.html",
+        )
+        .unwrap();
+        let html = absolutize_urls(
+            r##"<p><a href="#note-1">1</a> <a href="../about">about</a></p>
+            <img src="/img/a.jpg" srcset="a-300.jpg 300w, //cdn.example.net/a-800.jpg 800w">"##,
+            &base,
+        );
+        assert!(html.contains(r##"href="#note-1""##));
+        assert!(html.contains(r#"href="https://example.com/blog/about""#));
+        assert!(html.contains(r#"src="https://example.com/img/a.jpg""#));
+        assert!(html.contains(
+            r#"srcset="https://example.com/blog/2024/a-300.jpg 300w, https://cdn.example.net/a-800.jpg 800w""#
+        ));
+
+        let base = document_base(
+            r#"<html><head><base href="/static/"></head><body></body></html>"#,
+            "https://example.com/blog/post",
+        )
+        .unwrap();
+        assert_eq!(base.as_str(), "https://example.com/static/");
+    }
+
+    #[test]
+    fn clean_url_drops_tracking_and_amp_variants() {
+        let cases = [
+            (
+                "https://example.com/story?id=4&utm_source=rss&utm_medium=feed&fbclid=abc",
+                "https://example.com/story?id=4",
+            ),
+            ("https://example.com/story?ref_src=twsrc#comments", "https://example.com/story#comments"),
+            // `ref` often means something, e.g. a branch on GitHub.
+            ("https://github.com/o/r/blob/x.rs?ref=dev", "https://github.com/o/r/blob/x.rs?ref=dev"),
+            ("https://amp.example.com/news/story/amp/", "https://example.com/news/story/"),
+            ("https://m.example.com/amp/news/story?amp=1", "https://example.com/news/story"),
+            ("https://en.m.wikipedia.org/wiki/Rust", "https://en.wikipedia.org/wiki/Rust"),
+            ("https://www.example.com/story.amp.html", "https://www.example.com/story.html"),
+            (
+                "https://example-com.cdn.ampproject.org/c/s/example.com/news/story?outputType=amp",
+                "https://example.com/news/story",
+            ),
+            ("https://www.google.com/amp/s/example.com/story", "https://example.com/story"),
+            ("https://blog.amp.dev/2024/post", "https://blog.amp.dev/2024/post"),
+            ("mailto:someone@example.com", "mailto:someone@example.com"),
+        ];
+        for (link, expected) in cases {
+            assert_eq!(clean_url(link), expected, "{}", link);
+        }
+    }
+
+    #[test]
+    fn canonical_link_is_followed_unless_it_points_home() {
+        let page = "https://m.example.com/news/story?utm_source=x";
+        let html = r#"<html><head><link rel="canonical" href="/news/story?utm_campaign=y"></head></html>"#;
+        assert_eq!(canonical_link(html, page).as_deref(), Some("https://example.com/news/story"));
+        let home = r#"<html><head><link rel="canonical" href="https://example.com/"></head></html>"#;
+        assert_eq!(canonical_link(home, page), None);
+        assert_eq!(canonical_link("<html><head></head></html>", page), None);
+    }
+}
@@ -0,0 +1,413 @@
+//! Translates the XPath subset used by site extraction rules into CSS selectors
+//! `dom_query` understands. Paths, `*`, `|`, attribute tests, `contains`,
+//! `starts-with`, `ends-with`, `not`, `and`/`or`, positions and nested paths
+//! (as `:has`) are supported; axes, `..` and selecting attributes or text are not.
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    DoubleSlash,
+    Slash,
+    Pipe,
+    Open,
+    Close,
+    OpenParen,
+    CloseParen,
+    Comma,
+    At,
+    Eq,
+    NotEq,
+    Dot,
+    Star,
+    Name(String),
+    Str(String),
+    Num(usize),
+}
+
+fn tokenize(xpath: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let chars: Vec<char> = xpath.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        i += 1;
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '/' if chars.get(i) == Some(&'/') => {
+                i += 1;
+                Token::DoubleSlash
+            }
+            '/' => Token::Slash,
+            '|' => Token::Pipe,
+            '[' => Token::Open,
+            ']' => Token::Close,
+            '(' => Token::OpenParen,
+            ')' => Token::CloseParen,
+            ',' => Token::Comma,
+            '@' => Token::At,
+            '=' => Token::Eq,
+            '!' if chars.get(i) == Some(&'=') => {
+                i += 1;
+                Token::NotEq
+            }
+            '.' if chars.get(i) == Some(&'.') => return Err("parent steps (..) are not supported".to_string()),
+            '.' => Token::Dot,
+            '*' => Token::Star,
+            '\'' | '"' => {
+                let end = chars[i..]
+                    .iter()
+                    .position(|&ch| ch == c)
+                    .ok_or_else(|| "unterminated string".to_string())?;
+                let value: String = chars[i..i + end].iter().collect();
+                i += end + 1;
+                Token::Str(value)
+            }
+            c if c.is_ascii_digit() => {
+                let mut value = c.to_string();
+                while let Some(d) = chars.get(i).filter(|d| d.is_ascii_digit()) {
+                    value.push(*d);
+                    i += 1;
+                }
+                Token::Num(value.parse().map_err(|_| format!("bad number {}", value))?)
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut name = c.to_string();
+                while let Some(&ch) = chars.get(i) {
+                    if ch.is_alphanumeric() || matches!(ch, '_' | '-' | '.') {
+                        name.push(ch);
+                        i += 1;
+                    } else if ch == ':' && chars.get(i + 1) == Some(&':') {
+                        return Err(format!("axis {}:: is not supported", name));
+                    } else {
+                        break;
+                    }
+                }
+                Token::Name(name)
+            }
+            c => return Err(format!("unexpected character '{}'", c)),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Axis {
+    Child,
+    Descendant,
+}
+
+#[derive(Debug)]
+struct Step {
+    axis: Axis,
+    name: String,
+    predicates: Vec<Expr>,
+}
+
+#[derive(Debug)]
+enum Expr {
+    Attr(String),
+    Str(String),
+    Num(usize),
+    Call(String, Vec<Expr>),
+    Eq(Box<Expr>, Box<Expr>),
+    NotEq(Box<Expr>, Box<Expr>),
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+    Path(Vec<Step>),
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), String> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            other => Err(format!("expected {:?}, found {:?}", expected, other)),
+        }
+    }
+
+    fn is_name(&self, name: &str) -> bool {
+        matches!(self.peek(), Some(Token::Name(n)) if n == name)
+    }
+
+    fn union(&mut self) -> Result<Vec<Vec<Step>>, String> {
+        let mut paths = vec![self.path()?];
+        while self.peek() == Some(&Token::Pipe) {
+            self.next();
+            paths.push(self.path()?);
+        }
+        Ok(paths)
+    }
+
+    /// A location path; without a leading slash it is relative to the context node.
+    fn path(&mut self) -> Result<Vec<Step>, String> {
+        if self.peek() == Some(&Token::Dot) {
+            self.next();
+            if !matches!(self.peek(), Some(Token::Slash | Token::DoubleSlash)) {
+                return Err("selecting the context node (.) is not supported".to_string());
+            }
+        }
+        let mut axis = match self.peek() {
+            Some(Token::DoubleSlash) => {
+                self.next();
+                Axis::Descendant
+            }
+            Some(Token::Slash) => {
+                self.next();
+                Axis::Child
+            }
+            _ => Axis::Child,
+        };
+        let mut steps = Vec::new();
+        loop {
+            steps.push(self.step(axis)?);
+            axis = match self.peek() {
+                Some(Token::DoubleSlash) => Axis::Descendant,
+                Some(Token::Slash) => Axis::Child,
+                _ => break,
+            };
+            self.next();
+        }
+        Ok(steps)
+    }
+
+    fn step(&mut self, axis: Axis) -> Result<Step, String> {
+        let name = match self.next() {
+            Some(Token::Star) => "*".to_string(),
+            Some(Token::Name(name)) if self.peek() == Some(&Token::OpenParen) => {
+                return Err(format!("selecting {}() is not supported", name));
+            }
+            Some(Token::Name(name)) => name.to_ascii_lowercase(),
+            Some(Token::At) => return Err("selecting attributes is not supported".to_string()),
+            other => return Err(format!("expected an element name, found {:?}", other)),
+        };
+        let mut predicates = Vec::new();
+        while self.peek() == Some(&Token::Open) {
+            self.next();
+            predicates.push(self.or()?);
+            self.expect(Token::Close)?;
+        }
+        Ok(Step { axis, name, predicates })
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut terms = vec![self.and()?];
+        while self.is_name("or") {
+            self.next();
+            terms.push(self.and()?);
+        }
+        Ok(if terms.len() == 1 { terms.remove(0) } else { Expr::Or(terms) })
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut terms = vec![self.comparison()?];
+        while self.is_name("and") {
+            self.next();
+            terms.push(self.comparison()?);
+        }
+        Ok(if terms.len() == 1 { terms.remove(0) } else { Expr::And(terms) })
+    }
+
+    fn comparison(&mut self) -> Result<Expr, String> {
+        let left = self.primary()?;
+        match self.peek() {
+            Some(Token::Eq) => {
+                self.next();
+                Ok(Expr::Eq(Box::new(left), Box::new(self.primary()?)))
+            }
+            Some(Token::NotEq) => {
+                self.next();
+                Ok(Expr::NotEq(Box::new(left), Box::new(self.primary()?)))
+            }
+            _ => Ok(left),
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        match self.peek().cloned() {
+            Some(Token::At) => {
+                self.next();
+                match self.next() {
+                    Some(Token::Name(name)) => Ok(Expr::Attr(name.to_ascii_lowercase())),
+                    other => Err(format!("expected an attribute name, found {:?}", other)),
+                }
+            }
+            Some(Token::Str(value)) => {
+                self.next();
+                Ok(Expr::Str(value))
+            }
+            Some(Token::Num(n)) => {
+                self.next();
+                Ok(Expr::Num(n))
+            }
+            Some(Token::OpenParen) => {
+                self.next();
+                let expr = self.or()?;
+                self.expect(Token::CloseParen)?;
+                Ok(expr)
+            }
+            Some(Token::Name(name)) if self.tokens.get(self.pos + 1) == Some(&Token::OpenParen) => {
+                self.pos += 2;
+                let mut args = Vec::new();
+                if self.peek() != Some(&Token::CloseParen) {
+                    args.push(self.or()?);
+                    while self.peek() == Some(&Token::Comma) {
+                        self.next();
+                        args.push(self.or()?);
+                    }
+                }
+                self.expect(Token::CloseParen)?;
+                Ok(Expr::Call(name, args))
+            }
+            // `contains(., 'x')` compares the element's text.
+            Some(Token::Dot) if matches!(self.tokens.get(self.pos + 1), Some(Token::Comma | Token::CloseParen)) => {
+                self.next();
+                Ok(Expr::Call("text".to_string(), Vec::new()))
+            }
+            _ => Ok(Expr::Path(self.path()?)),
+        }
+    }
+}
+
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn path_css(steps: &[Step]) -> Result<String, String> {
+    let mut css = String::new();
+    for (i, step) in steps.iter().enumerate() {
+        if i > 0 {
+            css.push_str(if step.axis == Axis::Child { " > " } else { " " });
+        }
+        css.push_str(&step_css(step)?);
+    }
+    Ok(css)
+}
+
+fn step_css(step: &Step) -> Result<String, String> {
+    let mut css = step.name.clone();
+    for predicate in &step.predicates {
+        css.push_str(&predicate_css(predicate, &step.name)?);
+    }
+    Ok(css)
+}
+
+/// The CSS for a predicate, appended to the compound selector of its step.
+fn predicate_css(expr: &Expr, element: &str) -> Result<String, String> {
+    let position = |n: usize| match element {
+        "*" => format!(":nth-child({})", n),
+        _ => format!(":nth-of-type({})", n),
+    };
+    match expr {
+        Expr::Attr(name) => Ok(format!("[{}]", name)),
+        Expr::Num(n) => Ok(position(*n)),
+        Expr::And(terms) => terms.iter().map(|t| predicate_css(t, element)).collect(),
+        Expr::Or(terms) => {
+            let terms: Result<Vec<String>, String> = terms.iter().map(|t| predicate_css(t, element)).collect();
+            Ok(format!(":is({})", terms?.join(", ")))
+        }
+        Expr::Eq(left, right) => comparison_css(left, right, element),
+        Expr::NotEq(left, right) => Ok(format!(":not({})", comparison_css(left, right, element)?)),
+        Expr::Call(name, args) => match (name.as_str(), args.as_slice()) {
+            ("not", [inner]) => Ok(format!(":not({})", predicate_css(inner, element)?)),
+            ("last", []) => Ok(match element {
+                "*" => ":last-child".to_string(),
+                _ => ":last-of-type".to_string(),
+            }),
+            ("contains", [Expr::Attr(name), Expr::Str(value)]) => Ok(format!("[{}*={}]", name, quote(value))),
+            ("contains", [Expr::Call(f, _), Expr::Str(value)]) if f == "text" || f == "string" => {
+                Ok(format!(":contains({})", quote(value)))
+            }
+            // The usual whole-class idiom: contains(concat(' ', normalize-space(@class), ' '), ' x ')
+            ("contains", [Expr::Call(concat, parts), Expr::Str(value)]) if concat == "concat" => match parts.as_slice() {
+                [Expr::Str(_), Expr::Call(f, inner), Expr::Str(_)] if f == "normalize-space" => match inner.as_slice() {
+                    [Expr::Attr(name)] => Ok(format!("[{}~={}]", name, quote(value.trim()))),
+                    _ => Err("unsupported concat() in contains()".to_string()),
+                },
+                _ => Err("unsupported concat() in contains()".to_string()),
+            },
+            ("starts-with", [Expr::Attr(name), Expr::Str(value)]) => Ok(format!("[{}^={}]", name, quote(value))),
+            ("ends-with", [Expr::Attr(name), Expr::Str(value)]) => Ok(format!("[{}$={}]", name, quote(value))),
+            _ => Err(format!("function {}() is not supported here", name)),
+        },
+        Expr::Path(steps) => {
+            let relative = path_css(steps)?;
+            match steps.first().map(|s| s.axis) {
+                Some(Axis::Child) => Ok(format!(":has(> {})", relative)),
+                _ => Ok(format!(":has({})", relative)),
+            }
+        }
+        Expr::Str(_) => Err("a bare string is not a condition".to_string()),
+    }
+}
+
+fn comparison_css(left: &Expr, right: &Expr, element: &str) -> Result<String, String> {
+    match (left, right) {
+        (Expr::Attr(name), Expr::Str(value)) | (Expr::Str(value), Expr::Attr(name)) => {
+            Ok(format!("[{}={}]", name, quote(value)))
+        }
+        (Expr::Call(f, args), Expr::Str(value)) if f == "normalize-space" => match args.as_slice() {
+            [Expr::Attr(name)] => Ok(format!("[{}={}]", name, quote(value.trim()))),
+            _ => Err("unsupported normalize-space() comparison".to_string()),
+        },
+        (Expr::Call(f, args), Expr::Num(n)) if f == "position" && args.is_empty() => {
+            predicate_css(&Expr::Num(*n), element)
+        }
+        _ => Err("unsupported comparison".to_string()),
+    }
+}
+
+/// Converts an XPath expression selecting elements into an equivalent CSS
+/// selector, or explains why it cannot be converted.
+pub fn xpath_to_css(xpath: &str) -> Result<String, String> {
+    let mut parser = Parser { tokens: tokenize(xpath)?, pos: 0 };
+    let paths = parser.union()?;
+    if let Some(token) = parser.peek() {
+        return Err(format!("unexpected {:?}", token));
+    }
+    let selectors: Result<Vec<String>, String> = paths.iter().map(|p| path_css(p)).collect();
+    Ok(selectors?.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn translates_common_rule_expressions() {
+        let cases = [
+            ("//article", "article"),
+            ("//div[@id='content']", "div[id=\"content\"]"),
+            ("//div[contains(@class, 'post-body')]//p", "div[class*=\"post-body\"] p"),
+            ("//*[@itemprop='articleBody']", "*[itemprop=\"articleBody\"]"),
+            (
+                "//div[contains(concat(' ',normalize-space(@class),' '),' entry ')]",
+                "div[class~=\"entry\"]",
+            ),
+            ("//div[@class='a'] | //section/p[1]", "div[class=\"a\"], section > p:nth-of-type(1)"),
+            ("//a[@rel='next' and starts-with(@href, '/page')]", "a[rel=\"next\"][href^=\"/page\"]"),
+            ("//div[not(@data-ad)]", "div:not([data-ad])"),
+            ("//figure[./img]", "figure:has(> img)"),
+            ("//p[contains(text(), 'Advertisement')]", "p:contains(\"Advertisement\")"),
+        ];
+        for (xpath, css) in cases {
+            assert_eq!(xpath_to_css(xpath).as_deref(), Ok(css), "{}", xpath);
+        }
+        assert!(xpath_to_css("//div/@class").is_err());
+        assert!(xpath_to_css("//p/..").is_err());
+        assert!(xpath_to_css("//div/following-sibling::p").is_err());
+    }
+}
@@ -1,6 +1,7 @@
 use chrono::{DateTime, TimeZone, Utc};
 use rsspub::feed::{Article, ArticleSource};
-use rsspub::epub_gen::{generate_epub_data, CoverTextConfig};
+use rsspub::epub_gen::{generate_epub_data, EpubOptions};
+use rsspub::models::HtmlProfile;
 use std::fs::File;
 use std::io::{Cursor, Read};
 use tempfile::NamedTempFile;
@@ -34,6 +35,7 @@ fn create_article(
         content: content.to_string(),
         pub_date,
         article_source: create_article_source(source, position),
+        ..Default::default()
     }
 }
 
@@ -49,12 +51,21 @@ fn create_simple_article(title: &str, source: &str, position: i64) -> Article {
     )
 }
 
+/// Options used by the tests unless they exercise a specific option.
+fn test_options() -> EpubOptions {
+    EpubOptions { image_timeout_seconds: 30, ..Default::default() }
+}
+
 /// Generates an EPUB and returns the data as a byte vector.
 async fn generate_epub_to_vec(articles: &[Article]) -> Vec<u8> {
+    generate_epub_to_vec_with(articles, test_options()).await
+}
+
+async fn generate_epub_to_vec_with(articles: &[Article], options: EpubOptions) -> Vec<u8> {
     let temp_file = NamedTempFile::new().expect("Failed to create temp file");
     let file = File::create(temp_file.path()).expect("Failed to create file");
     
-    generate_epub_data(articles, file, 30, CoverTextConfig::default())
+    generate_epub_data(articles, file, options)
         .await
         .expect("Failed to generate EPUB");
     
@@ -144,7 +155,7 @@ async fn test_generate_epub_empty_articles() {
     let temp_file = NamedTempFile::new().expect("Failed to create temp file");
     let file = File::create(temp_file.path()).expect("Failed to create file");
     
-    let result = generate_epub_data(&articles, file, 30, CoverTextConfig::default()).await;
+    let result = generate_epub_data(&articles, file, test_options()).await;
     
     // Should succeed even with empty articles (creates just the TOC)
     assert!(result.is_ok(), "EPUB generation should succeed with empty articles");
@@ -476,7 +487,7 @@ async fn test_empty_title() {
     let temp_file = NamedTempFile::new().expect("Failed to create temp file");
     let file = File::create(temp_file.path()).expect("Failed to create file");
     
-    let result = generate_epub_data(&articles, file, 30, CoverTextConfig::default()).await;
+    let result = generate_epub_data(&articles, file, test_options()).await;
     
     assert!(result.is_ok(), "Should handle empty title");
 }
@@ -701,3 +712,62 @@ async fn test_article_back_links() {
     assert!(chapter.contains("toc_my_feed.xhtml"), 
             "Article should have back link to source TOC");
 }
+
+#[tokio::test]
+async fn test_links_become_endnotes_when_enabled() {
+    let content = r#"<p>Read <a href="https://example.com/source?a=1&amp;b=2">the source</a> first.</p>"#;
+    let articles = vec![create_article("Linked", "https://example.com/linked", content, Utc::now(), "Feed", 0)];
+
+    let options = EpubOptions { link_endnotes: true, ..test_options() };
+    let mut archive = extract_epub(generate_epub_to_vec_with(&articles, options).await);
+    let chapter = read_epub_file(&mut archive, "chapter_0.xhtml").unwrap();
+
+    assert!(chapter.contains(r#"xmlns:epub="http://www.idpf.org/2007/ops""#));
+    assert!(chapter.contains(r##"the source<sup><a epub:type="noteref" href="#link-1" id="link-ref-1">1</a></sup>"##));
+    assert!(chapter.contains(r#"<aside epub:type="footnote" id="link-1">"#));
+    assert!(chapter.contains(r#"<a href="https://example.com/source?a=1&#38;b=2">"#));
+    assert!(!chapter.contains(r#"<a href="https://example.com/source?a=1&amp;b=2">the source</a>"#));
+
+    // Without the option links stay inline.
+    let mut archive = extract_epub(generate_epub_to_vec(&articles).await);
+    let chapter = read_epub_file(&mut archive, "chapter_0.xhtml").unwrap();
+    assert!(chapter.contains(r#">the source</a>"#));
+    assert!(!chapter.contains("noteref"));
+}
+
+#[tokio::test]
+async fn test_extended_profile_keeps_technical_markup() {
+    let content = r#"<table><colgroup><col span="2"></colgroup><tr><th>Year</th><th>Revenue</th></tr><tr><td>2024</td><td>1<wbr>000</td></tr></table>
+        <pre><code class="rust">fn main() {}</code></pre>
+        <figure><img src="chart.jpg" alt="Chart"><figcaption>Revenue by year</figcaption></figure>
+        <p>E = mc<sup>2</sup>, H<sub>2</sub>O, <abbr title="Earnings per share">EPS</abbr></p>
+        <dl><dt>Term</dt><dd>Definition</dd></dl>
+        <math display="block"><msup><mi>x</mi><mn>2</mn></msup></math>"#;
+    let articles = vec![create_article("Technical", "https://example.com/tech", content, Utc::now(), "Feed", 0)];
+
+    let mut archive = extract_epub(generate_epub_to_vec(&articles).await);
+    let chapter = read_epub_file(&mut archive, "chapter_0.xhtml").unwrap();
+    for markup in [
+        "<th>Year</th>",
+        r#"<col span="2" />"#,
+        "1<wbr />000",
+        r#"<pre><code class="rust">fn main() {}</code></pre>"#,
+        "<figcaption>Revenue by year</figcaption>",
+        "mc<sup>2</sup>",
+        "H<sub>2</sub>O",
+        r#"<abbr title="Earnings per share">EPS</abbr>"#,
+        "<dt>Term</dt><dd>Definition</dd>",
+        r#"<math xmlns="http://www.w3.org/1998/Math/MathML" display="block"><msup><mi>x</mi><mn>2</mn></msup></math>"#,
+    ] {
+        assert!(chapter.contains(markup), "Chapter should keep {}", markup);
+    }
+    let stylesheet = read_epub_file(&mut archive, "OEBPS/stylesheet.css").expect("Stylesheet should be included");
+    assert!(stylesheet.contains("figcaption"));
+
+    let options = EpubOptions { html_profile: HtmlProfile::Basic, ..test_options() };
+    let mut archive = extract_epub(generate_epub_to_vec_with(&articles, options).await);
+    let chapter = read_epub_file(&mut archive, "chapter_0.xhtml").unwrap();
+    assert!(!chapter.contains("<table>"), "Basic profile should flatten tables");
+    assert!(!chapter.contains("<math"), "Basic profile should drop MathML");
+    assert!(chapter.contains("Revenue by year"));
+}
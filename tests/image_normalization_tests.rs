@@ -0,0 +1,81 @@
+use rsspub::models::HtmlProfile;
+use rsspub::util::{clean_html, normalize_images};
+use std::path::Path;
+
+// ============================================================================
+// Test Helpers
+// ============================================================================
+
+/// Loads a fixture and returns the `src` of every image left after normalizing
+/// and cleaning it the way the EPUB generator does.
+fn image_sources(fixture: &str) -> Vec<String> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/lazy_images")
+        .join(fixture);
+    let html = std::fs::read_to_string(&path).expect("Failed to read fixture");
+    let cleaned = clean_html(&normalize_images(&html), HtmlProfile::default());
+
+    let doc = dom_query::Document::from(cleaned.as_str());
+    doc.select("img")
+        .nodes()
+        .iter()
+        .map(|img| img.attr("src").map(|s| s.to_string()).unwrap_or_default())
+        .collect()
+}
+
+// ============================================================================
+// Lazy Loading Tests
+// ============================================================================
+
+#[test]
+fn test_data_src_replaces_inline_placeholder() {
+    assert_eq!(image_sources("data_src.html"), vec!["https://example.com/images/photo.jpg"]);
+}
+
+#[test]
+fn test_lazy_srcset_is_preferred_over_lazy_src() {
+    assert_eq!(
+        image_sources("data_lazy_src.html"),
+        vec!["https://example.com/wp-content/uploads/2024/03/chart-768x512.png"]
+    );
+}
+
+#[test]
+fn test_data_srcset_with_density_descriptors() {
+    assert_eq!(
+        image_sources("data_srcset.html"),
+        vec!["https://cdn.example.com/w_300,q_80/story.jpg"]
+    );
+}
+
+#[test]
+fn test_noscript_fallback_replaces_placeholder() {
+    assert_eq!(image_sources("noscript.html"), vec!["https://example.com/photos/real.jpg"]);
+}
+
+// ============================================================================
+// Responsive Image Tests
+// ============================================================================
+
+#[test]
+fn test_srcset_picks_candidate_for_target_width() {
+    assert_eq!(image_sources("srcset.html"), vec!["https://example.com/img/medium.jpg"]);
+}
+
+#[test]
+fn test_picture_skips_unsupported_sources() {
+    assert_eq!(image_sources("picture.html"), vec!["https://example.com/img/hero-800.webp"]);
+}
+
+#[test]
+fn test_plain_images_are_unchanged() {
+    let html = r#"<p>Text <img src="https://example.com/a.jpg" alt="A"></p>"#;
+    assert!(normalize_images(html).contains(r#"<img src="https://example.com/a.jpg" alt="A">"#));
+    assert_eq!(normalize_images("<p>No images</p>"), "<p>No images</p>");
+}
+
+#[test]
+fn test_tracking_params_are_stripped_from_images() {
+    let html = r#"<p><img src="https://example.com/a.jpg?w=600&utm_source=rss&fbclid=x"></p>"#;
+    assert!(normalize_images(html).contains(r#"src="https://example.com/a.jpg?w=600""#));
+}
@@ -2,6 +2,8 @@ export type ScheduleType = "rss" | "read_it_later";
 
 export type ScheduleFrequency = "daily" | "weekly" | "monthly";
 
+export type ArticleSelection = "window" | "undelivered";
+
 export type Category = {
     id: number;
     name: string;
@@ -27,6 +29,7 @@ export type Schedule = {
     category_ids: number[];
     override_to_email: string | null;
     fetch_since_hours_override: number | null;
+    article_selection: ArticleSelection;
 };
 
 export type ScheduleDraft = {
@@ -40,4 +43,5 @@ export type ScheduleDraft = {
     categoryIds: string[];
     overrideToEmail: string;
     fetchSinceHoursOverride: number | "";
+    articleSelection: ArticleSelection;
 };